@@ -0,0 +1,35 @@
+// Fixed-base tables (ZKP::with_tables) against the generic exponentiation.
+// cargo bench --bench fixed_base
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use zkp_chaum_pedersen::zkp_cp::ZKP;
+
+fn bench_fixed_base(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fixed_base");
+    group.sample_size(20);
+
+    for (bits, name) in [(1024, "rfc5114-1024-160"), (2048, "rfc5114-2048-256"), (3072, "rfc3526-3072")] {
+        let plain = ZKP::named(name).unwrap();
+        let fast = ZKP::named(name).unwrap().with_tables();
+
+        //* One proof: prover commitment, verifier check
+        let (x, k) = (plain.random_secret(), plain.random_secret());
+        let ((y1, y2), (r1, r2)) = (plain.commit(&x), plain.commit(&k));
+        let c = plain.random_scalar();
+        let s = plain.solve(&k, &c, &x).expose();
+
+        for (label, zkp) in [("plain", &plain), ("tables", &fast)] {
+            group.bench_with_input(BenchmarkId::new(format!("commit/{}", label), bits), &k, |b, k| {
+                b.iter(|| zkp.commit(k))
+            });
+            group.bench_with_input(BenchmarkId::new(format!("verify/{}", label), bits), &s, |b, s| {
+                b.iter(|| zkp.verify(&r1, &r2, &y1, &y2, &c, s).is_ok())
+            });
+        }
+        group.bench_function(BenchmarkId::new("build", bits), |b| b.iter(|| ZKP::named(name).unwrap().with_tables()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_fixed_base);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+// Batch verification against one verify() per proof.
+// cargo bench --bench verify
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use zkp_chaum_pedersen::zkp_cp::{BatchEntry, Group, ZKP};
+
+//* n valid proofs, each for its own secret.
+fn entries<G: Group>(zkp: &ZKP<G>, n: usize) -> Vec<BatchEntry<G>> {
+    (0..n)
+        .map(|_| {
+            let (x, k) = (zkp.random_secret(), zkp.random_secret());
+            let ((y1, y2), (r1, r2)) = (zkp.commit(&x), zkp.commit(&k));
+            let c = zkp.random_scalar();
+            let s = zkp.solve(&k, &c, &x).expose();
+            BatchEntry { y1, y2, r1, r2, c, s }
+        })
+        .collect()
+}
+
+fn bench_group<G: Group>(c: &mut Criterion, name: &str, zkp: &ZKP<G>) {
+    let mut group = c.benchmark_group(format!("verify/{}", name));
+    group.sample_size(10);
+
+    for n in [16, 64, 256] {
+        let batch = entries(zkp, n);
+
+        group.bench_with_input(BenchmarkId::new("individual", n), &batch, |b, batch| {
+            b.iter(|| batch.iter().all(|e| zkp.verify(&e.r1, &e.r2, &e.y1, &e.y2, &e.c, &e.s).is_ok()))
+        });
+        group.bench_with_input(BenchmarkId::new("batch", n), &batch, |b, batch| {
+            b.iter(|| zkp.verify_batch(batch).is_ok())
+        });
+    }
+    group.finish();
+}
+
+fn bench_verify(c: &mut Criterion) {
+    bench_group(c, "modp-2048", &ZKP::named("rfc5114-2048-256").unwrap());
+    bench_group(c, "ristretto255", &ZKP::ristretto255());
+}
+
+criterion_group!(benches, bench_verify);
+criterion_main!(benches);
@@ -0,0 +1,159 @@
+// Batch verification of Chaum-Pedersen proofs (random linear combination).
+// N proofs, each r1 = alpha^s * y1^c and r2 = beta^s * y2^c, are checked with one equation:
+
+// prod r1_i^z_i * r2_i^w_i = alpha^(sum z_i s_i) * beta^(sum w_i s_i) * prod y1_i^(z_i c_i) * y2_i^(w_i c_i)
+
+// - z_i, w_i: 128-bit random weights, drawn by the verifier; a bad proof passes with probability <= 2^-128.
+// - Both sides are one multi-exponentiation (ZKP::multi_pow, multiexp.rs) instead of 4N full exponentiations.
+// - On failure the batch is split in halves until the bad proofs are found (fresh weights each time).
+// - Elements must be validated first (parse_element): the weights only bind elements of order q.
+
+use num_bigint::{BigUint, RandBigInt};
+use crate::group::Group;
+use crate::zkp_cp::{ZkpError, ZKP};
+
+//* Weight size; soundness error 2^-WEIGHT_BITS per batch.
+pub const WEIGHT_BITS: u64 = 128;
+
+//* One proof of log_alpha(y1) = log_beta(y2) with its challenge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchEntry<G: Group> {
+    pub y1: G::Element,
+    pub y2: G::Element,
+    pub r1: G::Element,
+    pub r2: G::Element,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+impl<G: Group> ZKP<G> {
+    //* verify_batch
+    // Ok if every proof verifies; Err(ZkpError::BatchFailed) with the indices of the others.
+    pub fn verify_batch(&self, entries: &[BatchEntry<G>]) -> Result<(), ZkpError> {
+        let mut failed = Vec::new();
+        self.bisect(entries, 0, &mut failed);
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(ZkpError::BatchFailed(failed))
+        }
+    }
+
+    fn bisect(&self, entries: &[BatchEntry<G>], offset: usize, failed: &mut Vec<usize>) {
+        match entries {
+            [] => {}
+            [entry] => {
+                if self.verify(&entry.r1, &entry.r2, &entry.y1, &entry.y2, &entry.c, &entry.s).is_err() {
+                    failed.push(offset);
+                }
+            }
+            _ if self.check_combined(entries) => {}
+            _ => {
+                let (left, right) = entries.split_at(entries.len() / 2);
+                self.bisect(left, offset, failed);
+                self.bisect(right, offset + left.len(), failed);
+            }
+        }
+    }
+
+    //* The combined equation for fresh weights.
+    fn check_combined(&self, entries: &[BatchEntry<G>]) -> bool {
+        let q = self.group.order();
+        let mut rng = rand::thread_rng();
+
+        let mut lhs: Vec<(&G::Element, BigUint)> = Vec::with_capacity(2 * entries.len());
+        let mut rhs: Vec<(&G::Element, BigUint)> = Vec::with_capacity(2 * entries.len() + 2);
+        let (mut alpha_exp, mut beta_exp) = (BigUint::from(0u32), BigUint::from(0u32));
+
+        for entry in entries {
+            let (z, w) = (rng.gen_biguint(WEIGHT_BITS), rng.gen_biguint(WEIGHT_BITS));
+            alpha_exp += &z * &entry.s;
+            beta_exp += &w * &entry.s;
+            rhs.push((&entry.y1, (&z * &entry.c) % q));
+            rhs.push((&entry.y2, (&w * &entry.c) % q));
+            lhs.push((&entry.r1, z));
+            lhs.push((&entry.r2, w));
+        }
+        rhs.push((&self.alpha, alpha_exp % q));
+        rhs.push((&self.beta, beta_exp % q));
+
+        self.multi_pow(&by_ref(&lhs)) == self.multi_pow(&by_ref(&rhs))
+    }
+}
+
+fn by_ref<'a, E>(terms: &'a [(&'a E, BigUint)]) -> Vec<(&'a E, &'a BigUint)> {
+    terms.iter().map(|(n, exp)| (*n, exp)).collect()
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    //* n honest proofs; the ones in `bad` get s + 1.
+    fn entries<G: Group>(zkp: &ZKP<G>, n: usize, bad: &[usize]) -> Vec<BatchEntry<G>> {
+        (0..n)
+            .map(|i| {
+                let x = zkp.random_secret();
+                let k = zkp.random_secret();
+                let ((y1, y2), (r1, r2)) = (zkp.commit(&x), zkp.commit(&k));
+                let c = zkp.random_scalar();
+                let s = zkp.solve(&k, &c, &x).expose();
+                let s = if bad.contains(&i) { (s + 1u32) % zkp.group.order() } else { s };
+                BatchEntry { y1, y2, r1, r2, c, s }
+            })
+            .collect()
+    }
+
+    fn check_batch<G: Group>(zkp: &ZKP<G>) {
+        assert!(zkp.verify_batch(&[]).is_ok());
+        assert!(zkp.verify_batch(&entries(zkp, 1, &[])).is_ok());
+        assert!(zkp.verify_batch(&entries(zkp, 20, &[])).is_ok());
+
+        //* Bad proofs anywhere are all found, and only them.
+        for bad in [vec![0], vec![19], vec![3, 4, 11], (0..20).collect()] {
+            match zkp.verify_batch(&entries(zkp, 20, &bad)) {
+                Err(ZkpError::BatchFailed(failed)) => assert_eq!(failed, bad),
+                other => panic!("expected BatchFailed({:?}), got {:?}", bad, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_modp() {
+        check_batch(&ZKP::modp());
+    }
+
+    #[test]
+    fn test_batch_ristretto255() {
+        check_batch(&ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_batch_cancelling_errors() {
+        //* Two valid proofs with their statements exchanged.
+        let zkp = ZKP::modp();
+        let mut batch = entries(&zkp, 2, &[]);
+        let (y1, y2) = (batch[0].y1.clone(), batch[0].y2.clone());
+        (batch[0].y1, batch[0].y2) = (batch[1].y1.clone(), batch[1].y2.clone());
+        (batch[1].y1, batch[1].y2) = (y1, y2);
+        assert!(matches!(zkp.verify_batch(&batch), Err(ZkpError::BatchFailed(failed)) if failed == vec![0, 1]));
+
+        //* s0 + 1 and s1 - 1 for the same statement; with equal weights the errors would cancel out.
+        let x = zkp.random_secret();
+        let mut batch = entries(&zkp, 2, &[]);
+        for entry in batch.iter_mut() {
+            let k = zkp.random_secret();
+            ((entry.y1, entry.y2), (entry.r1, entry.r2)) = (zkp.commit(&x), zkp.commit(&k));
+            entry.s = zkp.solve(&k, &entry.c, &x).expose();
+        }
+        let q = zkp.group.order();
+        batch[0].s = (&batch[0].s + 1u32) % q;
+        batch[1].s = (&batch[1].s + q - 1u32) % q;
+        assert!(matches!(zkp.verify_batch(&batch), Err(ZkpError::BatchFailed(failed)) if failed == vec![0, 1]));
+    }
+}
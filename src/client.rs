@@ -2,85 +2,302 @@ pub mod zkp_auth{ //* Make this module available.
     include!("./zkp_auth.rs");
 }
 
-use std::io::stdin;
-use num_bigint::BigUint;
-use zkp_chaum_pedersen::zkp_cp::ZKP;
+use std::{io::stdin, time::{SystemTime, UNIX_EPOCH}};
+use tonic::{transport::Channel, Code};
+use zeroize::{Zeroize, Zeroizing};
+use zkp_chaum_pedersen::{kdf::{self, KdfParams}, zkp_cp::{Group, GroupParams, ParamError, SecretScalar, ZKP}, Backend, PROTOCOL_VERSION};
 use zkp_auth::{auth_service_client::AuthServiceClient, RegisterRequest};
-use crate::zkp_auth::{AuthenticationAnswerRequest, AuthenticationChallengeRequest};
+use crate::zkp_auth::{AuthenticationAnswerRequest, AuthenticationChallengeRequest, AuthenticationProofRequest, KdfParameters, KdfParametersRequest, NonInteractiveProof, ParametersRequest, ParametersResponse, RotateCredentialsRequest, SessionRequest};
 
-#[tokio::main] //* Async function
-async fn main(){
-    let mut buf:String = String::new();
-    let addr_local = "http://127.0.0.1:50051".to_string();
-    let (alpha,beta,p,q) = ZKP::get_const();
-    let zkp = ZKP{
-        alpha: alpha.clone(),
-        beta: beta.clone(),
-        p : p.clone(),
-        q: q.clone()
-    };
+//* Every field the server sent must describe zkp exactly; zkp is built locally, never from the response.
+fn check_response<G: Group>(zkp: &ZKP<G>, params: &ParametersResponse) -> Result<(), ParamError> {
+    let group = &zkp.group;
+    let fields = [
+        ("group", params.group == group.name()),
+        ("p", params.p == group.modulus().to_bytes_be()),
+        ("q", params.q == group.order().to_bytes_be()),
+        ("alpha", params.alpha == group.encode(&zkp.alpha)),
+        ("beta", params.beta == group.encode(&zkp.beta)),
+    ];
 
-    let mut client = AuthServiceClient::connect(addr_local).await.expect("Cannot Connect to the server!");
+    if let Some((field, _)) = fields.iter().find(|(_, ok)| !ok) {
+        return Err(ParamError::Mismatch(field));
+    }
 
-    println!("💫💫 Successfully connected into a server.");
-    println!("[Proceed Registration] Enter your name: ");
-    stdin().read_line(&mut buf).expect("Cannot get user name");
+    //* Nobody knows log_alpha(beta) only if beta comes from the seed.
+    if !zkp.check_beta(&params.beta_seed) {
+        return Err(ParamError::Invalid("beta is not derived from the served seed"));
+    }
+    Ok(())
+}
 
-    let username = buf.trim().to_string();
-    buf.clear();
+//* Salt and KDF costs the server holds for this username.
+async fn fetch_kdf(client: &mut AuthServiceClient<Channel>, username: &str) -> (Vec<u8>, KdfParams) {
+    let kdf_req = KdfParametersRequest{ username: username.to_string() };
+    let k_resp = client.get_kdf_parameters(kdf_req).await.expect("Cannot get KDF parameters").into_inner();
+    let kdf = k_resp.kdf.expect("Missing KDF parameters");
 
-    println!("[Proceed Registration] Enter your password: ");
-    stdin().read_line(&mut buf).expect("Cannot get user password");
-    let password = BigUint::from_bytes_be(buf.trim().as_bytes()); //* To byte.
-    buf.clear();
+    (k_resp.salt, KdfParams{
+        version: kdf.version,
+        memory_kib: kdf.memory_kib,
+        iterations: kdf.iterations,
+        parallelism: kdf.parallelism,
+    })
+}
+
+//* Password -> secret x in [0, q)
+fn to_secret<G: Group>(zkp: &ZKP<G>, password: &str, salt: &[u8], kdf: &KdfParams) -> SecretScalar {
+    kdf.derive_secret(password.as_bytes(), salt, zkp.group.order()).expect("Cannot derive secret")
+}
+
+fn kdf_to_msg(kdf: &KdfParams) -> KdfParameters {
+    KdfParameters{
+        version: kdf.version,
+        memory_kib: kdf.memory_kib,
+        iterations: kdf.iterations,
+        parallelism: kdf.parallelism,
+    }
+}
 
-    let y1 = ZKP::pow(&alpha, &password, &p);
-    let y2 = ZKP::pow(&beta, &password, &p);
+//* Register y1, y2 computed from the password; false if the username is taken.
+async fn register<G: Group>(client: &mut AuthServiceClient<Channel>, zkp: &ZKP<G>, server_id: &str, username: &str, password: &str) -> bool {
+    //* Fresh salt; costs are the server's current floor.
+    let (_, kdf) = fetch_kdf(client, username).await;
+    let salt = kdf::gen_salt();
+
+    let x = to_secret(zkp, password, &salt, &kdf);
+    let (y1, y2) = zkp.commit(&x);
+    //* Proof of possession; servers may refuse registrations without it.
+    let proof = zkp.prove_noninteractive(&x, &zkp.registration_transcript(username, server_id));
 
     let register_req = RegisterRequest{
-        username: username.clone(),
-        y1: y1.to_bytes_be(),
-        y2: y2.to_bytes_be()
+        username: username.to_string(),
+        y1: zkp.group.encode(&y1),
+        y2: zkp.group.encode(&y2),
+        salt,
+        kdf: Some(kdf_to_msg(&kdf)),
+        proof: Some(NonInteractiveProof{
+            r1: zkp.group.encode(&proof.r1),
+            r2: zkp.group.encode(&proof.r2),
+            s: zkp.group.encode_scalar(&proof.s),
+        }),
     };
 
-    let _r_resp = client.register(register_req).await.expect("Cannot register");
-    println!("{:?}",_r_resp);
+    match client.register(register_req).await {
+        Ok(r_resp) => {
+            println!("{:?}",r_resp);
+            true
+        }
+        Err(status) if status.code() == Code::AlreadyExists => false,
+        Err(status) => panic!("Cannot register: {}", status),
+    }
+}
 
-    println!("👍 Successfully registered!");
+//* Replace the keys of username; proves the current password, installs the new one.
+async fn rotate<G: Group>(client: &mut AuthServiceClient<Channel>, zkp: &ZKP<G>, server_id: &str, username: &str, password: &str, new_password: &str) {
+    let (salt, kdf) = fetch_kdf(client, username).await;
+    let x = to_secret(zkp, password, &salt, &kdf);
 
-    println!("\n\n[Verification] Enter your password: ");
-    stdin().read_line(&mut buf).expect("Cannot get user password");
-    let trial = BigUint::from_bytes_be(buf.trim().as_bytes()); //* To byte.
-    buf.clear();
+    //* Fresh salt, same KDF costs.
+    let new_salt = kdf::gen_salt();
+    let new_x = to_secret(zkp, new_password, &new_salt, &kdf);
+    let (y1, y2) = zkp.commit(&new_x);
+    let (y1, y2) = (zkp.group.encode(&y1), zkp.group.encode(&y2));
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock before 1970").as_secs();
+    //* Proof of possession of the new key, bound into the proof for the current one.
+    let new_proof = zkp.prove_noninteractive(&new_x, &zkp.rotation_key_transcript(username, server_id, timestamp));
+    let proof = zkp.prove_noninteractive(&x, &zkp.rotation_transcript(username, server_id, timestamp, &y1, &y2, &new_salt, &kdf, Some(&new_proof)));
+
+    let rotate_req = RotateCredentialsRequest{
+        username: username.to_string(),
+        r1: zkp.group.encode(&proof.r1),
+        r2: zkp.group.encode(&proof.r2),
+        s: zkp.group.encode_scalar(&proof.s),
+        timestamp,
+        y1,
+        y2,
+        salt: new_salt,
+        kdf: Some(kdf_to_msg(&kdf)),
+        new_proof: Some(NonInteractiveProof{
+            r1: zkp.group.encode(&new_proof.r1),
+            r2: zkp.group.encode(&new_proof.r2),
+            s: zkp.group.encode_scalar(&new_proof.s),
+        }),
+    };
+
+    client.rotate_credentials(rotate_req).await.expect("Cannot rotate credentials");
+}
 
-    //* Generate r1, r2.
-    let k = ZKP::gen_rand(&q);
-    let r1 = ZKP::pow(&alpha, &k, &p);
-    let r2 = ZKP::pow(&beta, &k, &p);
+//* Commitment -> challenge -> solution; returns the session id.
+async fn login<G: Group>(client: &mut AuthServiceClient<Channel>, zkp: &ZKP<G>, server_id: &str, username: &str, password: &str) -> String {
+    let (salt, kdf) = fetch_kdf(client, username).await;
+    let trial = to_secret(zkp, password, &salt, &kdf);
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock before 1970").as_secs();
+
+    //* Generate r1, r2; k is bound to the secret and this login.
+    let k = zkp.nonce(&trial, &zkp.login_transcript(username, server_id, timestamp).fingerprint());
+    let (r1, r2) = zkp.commit(&k);
 
     let challenge_req = AuthenticationChallengeRequest{
-        username: username.clone(),
-        r1: r1.to_bytes_be(),
-        r2: r2.to_bytes_be()
+        username: username.to_string(),
+        r1: zkp.group.encode(&r1),
+        r2: zkp.group.encode(&r2)
     };
 
     let c_resp = client.create_authentication_challenge(challenge_req).await.expect("Cannot challange").into_inner();
     println!("{:?}",c_resp);
 
     let auth_id = c_resp.auth_id;
-    let challenge = BigUint::from_bytes_be(&c_resp.c);
+    let challenge = zkp.parse_scalar(&c_resp.c).expect("Invalid challenge");
+
+    //* c must come from our transcript and the server's nonce; never answer a challenge bound to another server or user.
+    let (y1, y2) = zkp.commit(&trial);
+    let transcript = zkp.interactive_transcript(username, server_id, &c_resp.nonce);
+    if challenge != zkp.challenge(&transcript, &y1, &y2, &r1, &r2) {
+        panic!("Challenge not bound to this login (wrong password or another server)");
+    }
 
     //* Generate a solution based on secret password.
     let solution = zkp.solve(&k, &challenge, &trial);
 
     let verify_req = AuthenticationAnswerRequest{
         auth_id: auth_id.clone(),
-        s: solution.to_bytes_be()
+        s: zkp.group.encode_scalar(&solution.expose())
     };
 
     let v_resp = client.verify_authentication(verify_req).await.expect("Failed to verify").into_inner();
     println!("{:?}",v_resp);
 
-    println!("Verified! Hello {}, your session id will be :{}", username, v_resp.session_id);
+    v_resp.session_id
+}
+
+//* Single round trip; the challenge is derived by hashing (Fiat-Shamir).
+async fn login_with_proof<G: Group>(client: &mut AuthServiceClient<Channel>, zkp: &ZKP<G>, server_id: &str, username: &str, password: &str) -> String {
+    let (salt, kdf) = fetch_kdf(client, username).await;
+    let trial = to_secret(zkp, password, &salt, &kdf);
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).expect("Clock before 1970").as_secs();
+
+    let proof = zkp.prove_noninteractive(&trial, &zkp.login_transcript(username, server_id, timestamp));
+
+    let proof_req = AuthenticationProofRequest{
+        username: username.to_string(),
+        r1: zkp.group.encode(&proof.r1),
+        r2: zkp.group.encode(&proof.r2),
+        s: zkp.group.encode_scalar(&proof.s),
+        timestamp
+    };
+
+    let v_resp = client.authenticate_with_proof(proof_req).await.expect("Failed to verify").into_inner();
+    println!("{:?}",v_resp);
+
+    v_resp.session_id
+}
+
+//* Registration, then login with a second password prompt.
+async fn run<G: Group>(client: &mut AuthServiceClient<Channel>, zkp: &ZKP<G>, server_id: &str, one_shot: bool) {
+    //* Passwords pass through buf; wiped after each one. Large enough that read_line never reallocates (leaving a copy behind).
+    let mut buf = Zeroizing::new(String::with_capacity(1024));
+
+    println!("[Proceed Registration] Enter your name: ");
+    stdin().read_line(&mut buf).expect("Cannot get user name");
+
+    let username = buf.trim().to_string();
+    buf.clear();
+
+    println!("[Proceed Registration] Enter your password: ");
+    stdin().read_line(&mut buf).expect("Cannot get user password");
+    let password = Zeroizing::new(buf.trim().to_string());
+    buf.zeroize();
+
+    if register(client, zkp, server_id, &username, &password).await {
+        println!("👍 Successfully registered!");
+    } else {
+        println!("{} is already registered; proceeding to login.", username);
+    }
+
+    println!("\n\n[Verification] Enter your password: ");
+    stdin().read_line(&mut buf).expect("Cannot get user password");
+    let trial = Zeroizing::new(buf.trim().to_string());
+    buf.zeroize();
+
+    let session_id = if one_shot {
+        login_with_proof(client, zkp, server_id, &username, &trial).await
+    } else {
+        login(client, zkp, server_id, &username, &trial).await
+    };
+
+    println!("Verified! Hello {}, your session id will be :{}", username, session_id);
 
-}
\ No newline at end of file
+    //* Same check a downstream service would do.
+    let session_req = SessionRequest{ session_id };
+    let s_resp = client.validate_session(session_req).await.expect("Invalid session").into_inner();
+    println!("Session of {} valid until {} (unix time)", s_resp.username, s_resp.expires_at);
+
+    println!("\n\n[Rotation] Enter a new password (empty to skip): ");
+    stdin().read_line(&mut buf).expect("Cannot get user password");
+    let new_password = Zeroizing::new(buf.trim().to_string());
+    buf.zeroize();
+
+    if !new_password.is_empty() {
+        rotate(client, zkp, server_id, &username, &trial, &new_password).await;
+        println!("🔑 Password changed!");
+    }
+}
+
+#[tokio::main] //* Async function
+async fn main(){
+    let addr_local = "http://127.0.0.1:50051".to_string();
+
+    //* Login flow; ZKP_LOGIN=challenge (default) | proof
+    let one_shot = std::env::var("ZKP_LOGIN").map(|mode| mode == "proof").unwrap_or(false);
+
+    //* Identity of the server, bound into every challenge; must match its ZKP_SERVER_ID.
+    let server_id = std::env::var("ZKP_SERVER_ID").unwrap_or(addr_local.trim_start_matches("http://").to_string());
+
+    let mut client = AuthServiceClient::connect(addr_local).await.expect("Cannot Connect to the server!");
+
+    println!("💫💫 Successfully connected into a server.");
+
+    let params = client.get_parameters(ParametersRequest{}).await.expect("Cannot get parameters").into_inner();
+    if params.protocol_version != PROTOCOL_VERSION {
+        panic!("{}", ParamError::UnsupportedVersion(params.protocol_version));
+    }
+    println!("Group: {}", params.group);
+
+    //* Groups the client accepts; the server only picks one of them, its numbers must match ours.
+    //* ZKP_GROUP=<named group> | modp | ristretto255 | file:<path> pins one, as on the server (needed for paramgen groups).
+    //* Unset: any named group (constant::NAMED_GROUPS) or ristretto255, by the name the server sends.
+    let spec = std::env::var("ZKP_GROUP").ok();
+    let allowed = match &spec {
+        Some(spec) => Backend::from_name(spec).is_some(),
+        None => params.group == "ristretto255" || ZKP::named_groups().contains(&params.group.as_str()),
+    };
+    if !allowed {
+        panic!("{}", ParamError::UnknownGroup(params.group));
+    }
+    let spec = spec.unwrap_or(params.group.clone());
+
+    match Backend::from_name(&spec) {
+        Some(Backend::ModP) => {
+            let zkp = if spec == "modp" {
+                ZKP::modp()
+            } else if let Some(path) = spec.strip_prefix("file:") {
+                let text = std::fs::read_to_string(path).expect("Cannot read ZKP_GROUP file");
+                let group = GroupParams::parse(&text).expect("Cannot parse ZKP_GROUP file");
+                group.validate().expect("Invalid ZKP_GROUP file");
+                group.zkp()
+            } else {
+                ZKP::named(&spec).expect("Unknown ZKP_GROUP")
+            };
+            check_response(&zkp, &params).expect("Server group differs from the accepted one");
+            run(&mut client, &zkp, &server_id, one_shot).await
+        }
+        Some(Backend::Ristretto255) => {
+            let zkp = ZKP::ristretto255();
+            check_response(&zkp, &params).expect("Server group differs from the accepted one");
+            run(&mut client, &zkp, &server_id, one_shot).await
+        }
+        None => panic!("{}", ParamError::UnknownGroup(spec)),
+    }
+}
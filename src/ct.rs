@@ -0,0 +1,434 @@
+// Constant-time arithmetic on secrets (x, k).
+// BigUint takes time that depends on the values (branches on carries, normalised lengths, early exits).
+// Here the work runs on crypto-bigint fixed-width limbs; the width depends only on the public modulus.
+
+// - sub_mul_mod(): s = k - c * x mod q (ZKP::solve)
+// - pow_mod(): base^exp mod p for a secret exp (ModPGroup commitments); fixed window, table lookups in constant time
+// - FixedBase: precomputed powers of alpha / beta; no squarings, one multiplication per 4 bits of exp
+// - reduce(), bits2int(): secrets from wide random or hashed bytes (random_secret, KDF, nonces)
+
+// Secrets come in and go out as SecretScalar bytes, never as BigUint; their Uint / DynResidue copies are wiped (Zeroizing).
+// The conversion at the edges only reveals the byte length of a secret (its leading zero bytes).
+// Timing harness: cargo test --release ct::test -- --ignored --nocapture --test-threads=1 (dudect, see test)
+
+use std::borrow::Cow;
+use crypto_bigint::{modular::runtime_mod::{DynResidue, DynResidueParams}, subtle::{ConditionallySelectable, ConstantTimeEq, ConstantTimeLess}, Uint, Word, Zero};
+use num_bigint::BigUint;
+use zeroize::Zeroizing;
+use crate::secret::SecretScalar;
+
+//* Widest modulus with a constant-time path; ModPGroup::check_params rejects wider groups.
+pub const MAX_BITS: u64 = 8192;
+
+//* f::<LIMBS>(args) for the narrowest width holding `bits`; None above MAX_BITS.
+//* Also used by the variable-time arithmetic on public values (multiexp.rs).
+macro_rules! by_width {
+    ($bits:expr, $f:ident($($arg:expr),*)) => {
+        match $bits {
+            0..=256 => Some($f::<{ crypto_bigint::U256::LIMBS }>($($arg),*)),
+            257..=512 => Some($f::<{ crypto_bigint::U512::LIMBS }>($($arg),*)),
+            513..=1024 => Some($f::<{ crypto_bigint::U1024::LIMBS }>($($arg),*)),
+            1025..=2048 => Some($f::<{ crypto_bigint::U2048::LIMBS }>($($arg),*)),
+            2049..=3072 => Some($f::<{ crypto_bigint::U3072::LIMBS }>($($arg),*)),
+            3073..=4096 => Some($f::<{ crypto_bigint::U4096::LIMBS }>($($arg),*)),
+            4097..=8192 => Some($f::<{ crypto_bigint::U8192::LIMBS }>($($arg),*)),
+            _ => None,
+        }
+    };
+}
+pub(crate) use by_width;
+
+//* Public values; n must fit in LIMBS limbs.
+pub(crate) fn to_uint<const LIMBS: usize>(n: &BigUint) -> Uint<LIMBS> {
+    *le_uint(&n.to_bytes_le())
+}
+
+//* Little-endian bytes (secrets included) -> Uint; the padded copy and the result are wiped.
+fn le_uint<const LIMBS: usize>(le: &[u8]) -> Zeroizing<Uint<LIMBS>> {
+    let mut bytes = Zeroizing::new(vec![0u8; Uint::<LIMBS>::BYTES]);
+    bytes[..le.len()].copy_from_slice(le);
+    Zeroizing::new(Uint::from_le_slice(&bytes))
+}
+
+fn secret_from_uint<const LIMBS: usize>(n: &Uint<LIMBS>) -> SecretScalar {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(Uint::<LIMBS>::BYTES));
+    for word in n.as_words() {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    SecretScalar::from_le_bytes(bytes)
+}
+
+pub(crate) fn from_uint<const LIMBS: usize>(n: &Uint<LIMBS>) -> BigUint {
+    let mut bytes = Zeroizing::new(Vec::with_capacity(Uint::<LIMBS>::BYTES));
+    for word in n.as_words() {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+    BigUint::from_bytes_le(&bytes)
+}
+
+//* Public values wider than the modulus are reduced first; borrowed (no copy) otherwise.
+pub(crate) fn fit<'a>(n: &'a BigUint, modulus: &BigUint) -> Cow<'a, BigUint> {
+    if n.bits() > modulus.bits() { Cow::Owned(n % modulus) } else { Cow::Borrowed(n) }
+}
+
+//* Same for secrets, with reduce(); never the case for k, x < q. None: use the secret as it is.
+fn fit_secret(n: &SecretScalar, modulus: &BigUint) -> Option<SecretScalar> {
+    (n.bits() > modulus.bits()).then(|| reduce(n.le_bytes(), modulus))
+}
+
+//* output = n mod modulus, n little-endian; modulus odd.
+//* In a width holding both, n * R^2 < R * modulus for any n, so the Montgomery form of n is already reduced.
+pub fn reduce(n: &[u8], modulus: &BigUint) -> SecretScalar {
+    let bits = (n.len() as u64 * 8).max(modulus.bits());
+    let fixed = if modulus.bit(0) { by_width!(bits, reduce_fixed(n, modulus)) } else { None };
+    fixed.unwrap_or_else(|| SecretScalar::new(BigUint::from_bytes_le(n) % modulus))
+}
+
+fn reduce_fixed<const LIMBS: usize>(n: &[u8], modulus: &BigUint) -> SecretScalar {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(modulus));
+    let residue = Zeroizing::new(DynResidue::new(&le_uint::<LIMBS>(n), params));
+    secret_from_uint(&Zeroizing::new(residue.retrieve()))
+}
+
+//* bits2int (RFC 6979, 2.3.2) of big-endian bytes: the leftmost q.bits() bits; None unless in [1, q).
+pub fn bits2int(be: &[u8], q: &BigUint) -> Option<SecretScalar> {
+    let shift = (be.len() * 8).saturating_sub(q.bits() as usize);
+    let mut le = Zeroizing::new(be.to_vec());
+    le.reverse();
+
+    let bits = (be.len() as u64 * 8).max(q.bits());
+    by_width!(bits, bits2int_fixed(&le, shift, q)).unwrap_or_else(|| {
+        let k = SecretScalar::new(BigUint::from_bytes_le(&le) >> shift);
+        (k.bits() > 0 && k.expose() < *q).then_some(k)
+    })
+}
+
+fn bits2int_fixed<const LIMBS: usize>(le: &[u8], shift: usize, q: &BigUint) -> Option<SecretScalar> {
+    let k = Zeroizing::new(le_uint::<LIMBS>(le).shr_vartime(shift));
+    let in_range = !k.is_zero() & k.ct_lt(&to_uint::<LIMBS>(q));
+    bool::from(in_range).then(|| secret_from_uint(&k))
+}
+
+//* output = k - c * x mod q; q odd.
+pub fn sub_mul_mod(k: &SecretScalar, c: &BigUint, x: &SecretScalar, q: &BigUint) -> SecretScalar {
+    //* Montgomery form needs an odd modulus; q is an odd prime in every valid group.
+    if !q.bit(0) {
+        return SecretScalar::new(sub_mul_mod_vartime(&k.expose(), c, &x.expose(), q));
+    }
+    let (k_fit, x_fit) = (fit_secret(k, q), fit_secret(x, q));
+    let (k, c, x) = (k_fit.as_ref().unwrap_or(k), fit(c, q), x_fit.as_ref().unwrap_or(x));
+    by_width!(q.bits(), sub_mul_mod_fixed(k, &c, x, q))
+        .unwrap_or_else(|| SecretScalar::new(sub_mul_mod_vartime(&k.expose(), &c, &x.expose(), q)))
+}
+
+fn sub_mul_mod_fixed<const LIMBS: usize>(k: &SecretScalar, c: &BigUint, x: &SecretScalar, q: &BigUint) -> SecretScalar {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(q));
+    let secret = |n: &SecretScalar| Zeroizing::new(DynResidue::new(&le_uint::<LIMBS>(n.le_bytes()), params));
+
+    let (k, x) = (secret(k), secret(x));
+    let s = Zeroizing::new(*k - DynResidue::new(&to_uint::<LIMBS>(c), params) * *x);
+    secret_from_uint(&Zeroizing::new(s.retrieve()))
+}
+
+//* BigUint arithmetic; branches on k >= c * x, copies are not wiped. Groups outside the constant-time path (and the timing test).
+pub(crate) fn sub_mul_mod_vartime(k: &BigUint, c: &BigUint, x: &BigUint, q: &BigUint) -> BigUint {
+    //* If k is less than c*x, it will occur downflow.
+    if *k >= c * x {
+        (k - c * x).modpow(&BigUint::from(1u32), q)
+    } else {
+        //* (c*x - k) mod q can be 0; reduce again so that s stays below q.
+        (q - (c * x - k).modpow(&BigUint::from(1u32), q)) % q
+    }
+}
+
+//* output = base^exp mod p; p odd, base of order q.
+//* Every exponent costs the same: exp_bits = q.bits() squarings, one table lookup per window.
+pub fn pow_mod(base: &BigUint, exp: &SecretScalar, p: &BigUint, q: &BigUint) -> BigUint {
+    if !p.bit(0) {
+        return base.modpow(&exp.expose(), p);
+    }
+    let exp_fit = fit_secret(exp, q);
+    let (base, exp) = (fit(base, p), exp_fit.as_ref().unwrap_or(exp));
+    by_width!(p.bits(), pow_mod_fixed(&base, exp, p, q.bits() as usize)).unwrap_or_else(|| base.modpow(&exp.expose(), p))
+}
+
+fn pow_mod_fixed<const LIMBS: usize>(base: &BigUint, exp: &SecretScalar, p: &BigUint, exp_bits: usize) -> BigUint {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(p));
+    let base = DynResidue::new(&to_uint::<LIMBS>(base), params);
+
+    from_uint(&base.pow_bounded_exp(&le_uint::<LIMBS>(exp.le_bytes()), exp_bits).retrieve())
+}
+
+//* Fixed-base table: base^(d * 16^i) for every window i of exp and every digit d, in Montgomery form.
+//* base^exp = prod_i T[i][digit_i(exp)]; exp_bits / 4 multiplications instead of exp_bits squarings.
+//* Size: exp_bits / 4 * 16 * p_bits / 8 bytes (256 KiB for a 2048-bit p and 256-bit q).
+pub const WINDOW: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct FixedBase {
+    base: BigUint,
+    p: BigUint,
+    q: BigUint,
+    entries: Vec<Word>, //* [window][digit][limb]; empty if p has no constant-time path (pow_mod falls back too).
+}
+
+impl FixedBase {
+    pub fn new(base: &BigUint, p: &BigUint, q: &BigUint) -> Self {
+        let exp_bits = q.bits() as usize;
+        let entries = if p.bit(0) { by_width!(p.bits(), build_table(&fit(base, p), p, exp_bits)).unwrap_or_default() } else { Vec::new() };
+        FixedBase { base: base.clone(), p: p.clone(), q: q.clone(), entries }
+    }
+
+    pub fn base(&self) -> &BigUint {
+        &self.base
+    }
+
+    //* Public exponent; entries are indexed directly.
+    pub fn pow(&self, exp: &BigUint) -> BigUint {
+        self.lookup(&fit(exp, &self.q).to_bytes_le(), false).unwrap_or_else(|| self.base.modpow(exp, &self.p))
+    }
+
+    //* Secret exponent; every entry of a window is read, the digit picks one with a constant-time select.
+    pub fn pow_secret(&self, exp: &SecretScalar) -> BigUint {
+        let exp_fit = fit_secret(exp, &self.q);
+        self.lookup(exp_fit.as_ref().unwrap_or(exp).le_bytes(), true).unwrap_or_else(|| pow_mod(&self.base, exp, &self.p, &self.q))
+    }
+
+    //* exp little-endian, below q.
+    fn lookup(&self, exp: &[u8], secret: bool) -> Option<BigUint> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        by_width!(self.p.bits(), pow_table(&self.entries, exp, &self.p, secret))
+    }
+}
+
+fn build_table<const LIMBS: usize>(base: &BigUint, p: &BigUint, exp_bits: usize) -> Vec<Word> {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(p));
+    let windows = exp_bits.div_ceil(WINDOW);
+    let mut entries = Vec::with_capacity(windows * (1 << WINDOW) * LIMBS);
+
+    //* window_base = base^(16^i)
+    let mut window_base = DynResidue::new(&to_uint::<LIMBS>(base), params);
+    for _ in 0..windows {
+        let mut power = DynResidue::one(params);
+        for _ in 0..(1 << WINDOW) {
+            entries.extend_from_slice(power.as_montgomery().as_words());
+            power *= window_base;
+        }
+        window_base = power;
+    }
+    entries
+}
+
+fn pow_table<const LIMBS: usize>(entries: &[Word], exp: &[u8], p: &BigUint, secret: bool) -> BigUint {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(p));
+    let exp = le_uint::<LIMBS>(exp);
+    let entry = |index: usize| Uint::<LIMBS>::from_words(std::array::from_fn(|limb| entries[index * LIMBS + limb]));
+
+    let mut acc = DynResidue::one(params);
+    for window in 0..entries.len() / ((1 << WINDOW) * LIMBS) {
+        let (bit, first) = (window * WINDOW, window << WINDOW);
+        let digit = (exp.as_words()[bit / Word::BITS as usize] >> (bit % Word::BITS as usize)) & ((1 << WINDOW) - 1);
+
+        let power = if secret {
+            let mut power = Uint::<LIMBS>::ZERO;
+            for d in 0..(1 << WINDOW) {
+                power.conditional_assign(&entry(first + d as usize), d.ct_eq(&digit));
+            }
+            power
+        } else {
+            entry(first + digit as usize)
+        };
+        acc *= DynResidue::from_montgomery(power, params);
+    }
+    from_uint(&acc.retrieve())
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{hint::black_box, time::Instant};
+    use num_bigint::RandBigInt;
+    use rand::Rng;
+    use crate::zkp_cp::ZKP;
+
+    fn secret(n: &BigUint) -> SecretScalar {
+        SecretScalar::new(n.clone())
+    }
+
+    #[test]
+    fn test_sub_mul_mod() {
+        //* Tiny group: every k, c, x
+        let q = BigUint::from(11u32);
+        for k in 0..11u32 {
+            for c in 0..11u32 {
+                for x in 0..11u32 {
+                    let (k, c, x) = (BigUint::from(k), BigUint::from(c), BigUint::from(x));
+                    assert_eq!(sub_mul_mod(&secret(&k), &c, &secret(&x), &q).expose(), sub_mul_mod_vartime(&k, &c, &x, &q));
+                }
+            }
+        }
+
+        //* Every width, including a 160-bit q in 256-bit limbs and the 3071-bit q of rfc3526-3072
+        for name in ZKP::named_groups() {
+            let q = ZKP::named(name).unwrap().group.q;
+            for _ in 0..8 {
+                let (k, c, x) = (ZKP::gen_rand(&q), ZKP::gen_rand(&q), ZKP::gen_rand(&q));
+                assert_eq!(sub_mul_mod(&secret(&k), &c, &secret(&x), &q).expose(), sub_mul_mod_vartime(&k, &c, &x, &q), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pow_mod() {
+        for name in ZKP::named_groups() {
+            let zkp = ZKP::named(name).unwrap();
+            let (p, q) = (&zkp.group.p, &zkp.group.q);
+            for exp in [BigUint::from(0u32), BigUint::from(1u32), q - 1u32, ZKP::gen_rand(q)] {
+                assert_eq!(pow_mod(&zkp.alpha, &secret(&exp), p, q), zkp.alpha.modpow(&exp, p), "{}", name);
+            }
+            //* Exponent >= q: reduced, alpha has order q
+            assert_eq!(pow_mod(&zkp.beta, &secret(&(q * 3u32 + 5u32)), p, q), zkp.beta.modpow(&BigUint::from(5u32), p));
+        }
+    }
+
+    #[test]
+    fn test_reduce() {
+        //* Wide values (KDF output, random_secret) in every width; all-ones is the largest n of its width.
+        for name in ZKP::named_groups() {
+            let q = ZKP::named(name).unwrap().group.q;
+            for n in [vec![0xFF; 64], vec![0xFF; q.bits().div_ceil(8) as usize + 16], ZKP::gen_rand(&(&q << 128)).to_bytes_le(), vec![]] {
+                assert_eq!(reduce(&n, &q).expose(), BigUint::from_bytes_le(&n) % &q, "{}", name);
+            }
+        }
+        //* Even modulus: BigUint fallback
+        assert_eq!(reduce(&[0xFF; 8], &BigUint::from(10u32)).expose(), BigUint::from(u64::MAX % 10));
+    }
+
+    #[test]
+    fn test_bits2int() {
+        //* 11 = 0b1011: the leftmost 4 bits of the byte, accepted only in [1, 11)
+        let q = BigUint::from(11u32);
+        for byte in 0..=255u8 {
+            let expected = byte >> 4;
+            let k = bits2int(&[byte], &q);
+            assert_eq!(k.map(|k| k.expose()), (expected > 0 && expected < 11).then(|| BigUint::from(expected)));
+        }
+
+        let q = ZKP::named("rfc5114-2048-256").unwrap().group.q;
+        let t = (q.clone() - 1u32).to_bytes_be();
+        assert_eq!(bits2int(&t, &q).unwrap().expose(), q - 1u32);
+        assert!(bits2int(&[0u8; 32], &ZKP::named("rfc5114-2048-256").unwrap().group.q).is_none());
+    }
+
+    #[test]
+    fn test_fixed_base() {
+        //* Tiny group: every exponent, 4-bit q (one window)
+        let (p, q, g) = (BigUint::from(23u32), BigUint::from(11u32), BigUint::from(4u32));
+        let table = FixedBase::new(&g, &p, &q);
+        for exp in 0..30u32 {
+            let exp = BigUint::from(exp);
+            assert_eq!(table.pow(&exp), g.modpow(&exp, &p));
+            assert_eq!(table.pow_secret(&secret(&exp)), g.modpow(&exp, &p));
+        }
+
+        //* Even modulus: no table, same results
+        let (p, q, g) = (BigUint::from(24u32), BigUint::from(2u32), BigUint::from(5u32));
+        let table = FixedBase::new(&g, &p, &q);
+        assert!(table.entries.is_empty());
+        assert_eq!(table.pow_secret(&secret(&BigUint::from(3u32))), BigUint::from(5u32));
+    }
+
+    //* dudect (Reparaz, Balasch, Verbauwhede 2017): time one operation on two classes of inputs,
+    //* a fixed one and a random one, interleaved at random. Welch's t-test on the two timing
+    //* distributions; |t| > 4.5 means the time depends on the input.
+    //* Measurements above a percentile are cropped (interrupts, page faults); the largest |t| is kept.
+    fn dudect<T>(inputs: &[(usize, T)], op: impl Fn(&T)) -> f64 {
+        let mut times: Vec<(usize, f64)> = inputs
+            .iter()
+            .map(|(class, input)| {
+                let start = Instant::now();
+                op(input);
+                (*class, start.elapsed().as_nanos() as f64)
+            })
+            .collect();
+
+        let mut sorted: Vec<f64> = times.iter().map(|(_, t)| *t).collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        times.retain(|(_, t)| *t <= sorted[sorted.len() * 99 / 100]);
+
+        [0.5, 0.75, 0.9, 1.0]
+            .iter()
+            .map(|crop| welch_t(&times, sorted[((sorted.len() - 1) as f64 * crop) as usize]))
+            .fold(0.0, |max: f64, t| max.max(t.abs()))
+    }
+
+    fn welch_t(times: &[(usize, f64)], below: f64) -> f64 {
+        let stats = |class: usize| {
+            let xs: Vec<f64> = times.iter().filter(|(c, t)| *c == class && *t <= below).map(|(_, t)| *t).collect();
+            let n = xs.len() as f64;
+            let mean = xs.iter().sum::<f64>() / n;
+            let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            (n, mean, var)
+        };
+        let ((n0, m0, v0), (n1, m1, v1)) = (stats(0), stats(1));
+        (m0 - m1) / (v0 / n0 + v1 / n1).sqrt()
+    }
+
+    //* Class 0: fixed input, class 1: random input.
+    fn classes<T>(samples: usize, fixed: impl Fn() -> T, random: impl Fn() -> T) -> Vec<(usize, T)> {
+        let mut rng = rand::thread_rng();
+        (0..samples)
+            .map(|_| if rng.gen() { (0, fixed()) } else { (1, random()) })
+            .collect()
+    }
+
+    const SAMPLES: usize = 20_000;
+    const THRESHOLD: f64 = 4.5;
+
+    #[test]
+    #[ignore] //* Timing; run locally with --release.
+    fn test_dudect_solve() {
+        let q = ZKP::named("rfc5114-2048-256").unwrap().group.q;
+        let c = BigUint::from(1u32);
+        let full = |n: BigUint| n | (BigUint::from(1u32) << (q.bits() - 8)); //* Same byte length in both classes
+
+        //* k = q - 1 >= x: the BigUint path always takes the k >= c * x branch; random inputs take either.
+        let fixed_x = full(ZKP::gen_rand(&q));
+        let inputs = classes(SAMPLES, || (&q - 1u32, fixed_x.clone()), || (full(ZKP::gen_rand(&q)), full(ZKP::gen_rand(&q))));
+
+        let secrets: Vec<_> = inputs.iter().map(|(class, (k, x))| (*class, (secret(k), secret(x)))).collect();
+        let t = dudect(&secrets, |(k, x)| { black_box(sub_mul_mod(k, &c, x, &q)); });
+        let t_vartime = dudect(&inputs, |(k, x)| { black_box(sub_mul_mod_vartime(k, &c, x, &q)); });
+        println!("solve: |t| = {:.2} (BigUint: {:.2})", t, t_vartime);
+        assert!(t < THRESHOLD, "solve leaks timing: |t| = {:.2}", t);
+    }
+
+    #[test]
+    #[ignore] //* Timing; run locally with --release.
+    fn test_dudect_commit() {
+        let zkp = ZKP::named("rfc5114-2048-256").unwrap();
+        let (p, q) = (&zkp.group.p, &zkp.group.q);
+        let mut rng = rand::thread_rng();
+        let low_weight = rng.gen_biguint_below(&BigUint::from(256u32));
+
+        //* A short exponent against full-size ones; square-and-multiply would show it at once.
+        let inputs = classes(SAMPLES / 4, || low_weight.clone(), || ZKP::gen_rand(q));
+
+        let secrets: Vec<_> = inputs.iter().map(|(class, x)| (*class, secret(x))).collect();
+        let t = dudect(&secrets, |x| { black_box(pow_mod(&zkp.alpha, x, p, q)); });
+        let t_vartime = dudect(&inputs, |x| { black_box(zkp.alpha.modpow(x, p)); });
+        let table = FixedBase::new(&zkp.alpha, p, q);
+        let t_table = dudect(&secrets, |x| { black_box(table.pow_secret(x)); });
+        println!("commit: |t| = {:.2}, with table {:.2} (BigUint: {:.2})", t, t_table, t_vartime);
+        assert!(t < THRESHOLD, "commit leaks timing: |t| = {:.2}", t);
+        assert!(t_table < THRESHOLD, "commit with table leaks timing: |t| = {:.2}", t_table);
+        //* The harness sees a leak when there is one.
+        assert!(t_vartime > THRESHOLD);
+    }
+}
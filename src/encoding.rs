@@ -0,0 +1,261 @@
+// Canonical binary encodings of parameters, statements (y1, y2) and transcripts (r1, r2, c, s).
+// version || kind || fields; every field is prefixed with its length (u32, big-endian).
+
+// - elements: Group::encode(), element_len() bytes; scalars: encode_scalar(), scalar_len() bytes
+// - parameters: Group::encode_params() fields, then alpha and beta
+// - decoding accepts exactly the bytes encoding produces, nothing else:
+//   other lengths, leading zeros, elements >= p, scalars >= q, trailing bytes are all rejected.
+//   A proof has a single encoding (s and s + q verify alike; only s is accepted).
+
+use std::fmt;
+use num_bigint::BigUint;
+use crate::group::Group;
+use crate::validation::{ParamError, ValidationError};
+use crate::zkp_cp::ZKP;
+
+pub const ENCODING_VERSION: u8 = 1;
+
+const KIND_PARAMS: u8 = 1;
+const KIND_STATEMENT: u8 = 2;
+const KIND_TRANSCRIPT: u8 = 3;
+
+//* Public key of a user; y1 = alpha^x, y2 = beta^x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement<G: Group> {
+    pub y1: G::Element,
+    pub y2: G::Element,
+}
+
+//* Commitment, challenge and response of one proof.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript<G: Group> {
+    pub r1: G::Element,
+    pub r2: G::Element,
+    pub c: BigUint,
+    pub s: BigUint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodingError {
+    UnsupportedVersion(u8),
+    WrongKind(u8), //* e.g. a statement decoded as a transcript.
+    Truncated, //* Missing bytes or fields.
+    TrailingBytes, //* Bytes or fields after the last one.
+    Invalid(&'static str, ValidationError), //* Malformed: field is not the canonical encoding.
+    Params(ParamError),
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::UnsupportedVersion(version) => write!(f, "unsupported encoding version {}", version),
+            EncodingError::WrongKind(kind) => write!(f, "unexpected kind {}", kind),
+            EncodingError::Truncated => f.write_str("truncated encoding"),
+            EncodingError::TrailingBytes => f.write_str("trailing bytes"),
+            EncodingError::Invalid(field, e) => write!(f, "{}: {}", field, e),
+            EncodingError::Params(e) => write!(f, "parameters: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+fn seal(kind: u8, fields: &[&[u8]]) -> Vec<u8> {
+    let mut out = vec![ENCODING_VERSION, kind];
+    for field in fields {
+        out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+//* Fields of an encoding of this kind.
+fn open(bytes: &[u8], kind: u8) -> Result<Vec<&[u8]>, EncodingError> {
+    let [version, found, rest @ ..] = bytes else { return Err(EncodingError::Truncated) };
+    if *version != ENCODING_VERSION {
+        return Err(EncodingError::UnsupportedVersion(*version));
+    }
+    if *found != kind {
+        return Err(EncodingError::WrongKind(*found));
+    }
+
+    let (mut rest, mut fields) = (rest, Vec::new());
+    while !rest.is_empty() {
+        let (len, tail) = rest.split_first_chunk::<4>().ok_or(EncodingError::Truncated)?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if tail.len() < len {
+            return Err(EncodingError::Truncated);
+        }
+        let (field, tail) = tail.split_at(len);
+        fields.push(field);
+        rest = tail;
+    }
+    Ok(fields)
+}
+
+//* Exactly N fields.
+fn fields<const N: usize>(fields: Vec<&[u8]>) -> Result<[&[u8]; N], EncodingError> {
+    match fields.len() {
+        n if n < N => Err(EncodingError::Truncated),
+        n if n > N => Err(EncodingError::TrailingBytes),
+        _ => Ok(fields.try_into().expect("length checked")),
+    }
+}
+
+impl<G: Group> ZKP<G> {
+    pub fn encode_parameters(&self) -> Vec<u8> {
+        let mut fields = self.group.encode_params();
+        fields.push(self.group.encode(&self.alpha));
+        fields.push(self.group.encode(&self.beta));
+        seal(KIND_PARAMS, &fields.iter().map(Vec::as_slice).collect::<Vec<_>>())
+    }
+
+    //* decode + check_params; tables are not built.
+    pub fn decode_parameters(bytes: &[u8]) -> Result<Self, EncodingError> {
+        let fields = open(bytes, KIND_PARAMS)?;
+        let [group_fields @ .., alpha, beta] = fields.as_slice() else { return Err(EncodingError::Truncated) };
+        let group_fields: Vec<Vec<u8>> = group_fields.iter().map(|field| field.to_vec()).collect();
+        let group = G::decode_params(&group_fields).ok_or(EncodingError::Params(ParamError::Invalid("group parameters are not canonical")))?;
+
+        let element = |field: &'static str, bytes: &[u8]| group.decode(bytes).ok_or(EncodingError::Invalid(field, ValidationError::Malformed));
+        let zkp = ZKP { alpha: element("alpha", alpha)?, beta: element("beta", beta)?, group, tables: None };
+        zkp.check_params().map_err(EncodingError::Params)?;
+        Ok(zkp)
+    }
+
+    pub fn encode_statement(&self, statement: &Statement<G>) -> Vec<u8> {
+        seal(KIND_STATEMENT, &[&self.group.encode(&statement.y1), &self.group.encode(&statement.y2)])
+    }
+
+    //* decode + validate (parse_element)
+    pub fn decode_statement(&self, bytes: &[u8]) -> Result<Statement<G>, EncodingError> {
+        let [y1, y2] = fields(open(bytes, KIND_STATEMENT)?)?;
+        Ok(Statement { y1: self.parse_field("y1", y1)?, y2: self.parse_field("y2", y2)? })
+    }
+
+    pub fn encode_transcript(&self, transcript: &Transcript<G>) -> Vec<u8> {
+        let group = &self.group;
+        seal(KIND_TRANSCRIPT, &[
+            &group.encode(&transcript.r1),
+            &group.encode(&transcript.r2),
+            &group.encode_scalar(&transcript.c),
+            &group.encode_scalar(&transcript.s),
+        ])
+    }
+
+    //* decode + validate (parse_element, parse_scalar)
+    pub fn decode_transcript(&self, bytes: &[u8]) -> Result<Transcript<G>, EncodingError> {
+        let [r1, r2, c, s] = fields(open(bytes, KIND_TRANSCRIPT)?)?;
+        Ok(Transcript {
+            r1: self.parse_field("r1", r1)?,
+            r2: self.parse_field("r2", r2)?,
+            c: self.parse_scalar(c).map_err(|e| EncodingError::Invalid("c", e))?,
+            s: self.parse_scalar(s).map_err(|e| EncodingError::Invalid("s", e))?,
+        })
+    }
+
+    fn parse_field(&self, field: &'static str, bytes: &[u8]) -> Result<G::Element, EncodingError> {
+        self.parse_element(bytes).map_err(|e| EncodingError::Invalid(field, e))
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn transcript<G: Group>(zkp: &ZKP<G>) -> (Statement<G>, Transcript<G>) {
+        let (x, k) = (zkp.random_secret(), zkp.random_secret());
+        let ((y1, y2), (r1, r2)) = (zkp.commit(&x), zkp.commit(&k));
+        let c = zkp.random_scalar();
+        let s = zkp.solve(&k, &c, &x).expose();
+        (Statement { y1, y2 }, Transcript { r1, r2, c, s })
+    }
+
+    fn check_round_trip<G: Group>(zkp: &ZKP<G>) {
+        let params = zkp.encode_parameters();
+        let decoded = ZKP::<G>::decode_parameters(&params).unwrap();
+        assert!(decoded.group == zkp.group && decoded.alpha == zkp.alpha && decoded.beta == zkp.beta);
+
+        let (statement, proof) = transcript(zkp);
+        let (statement_bytes, proof_bytes) = (zkp.encode_statement(&statement), zkp.encode_transcript(&proof));
+        assert_eq!(zkp.decode_statement(&statement_bytes), Ok(statement.clone()));
+        assert_eq!(zkp.decode_transcript(&proof_bytes), Ok(proof.clone()));
+        assert!(zkp.verify(&proof.r1, &proof.r2, &statement.y1, &statement.y2, &proof.c, &proof.s).is_ok());
+
+        //* Fixed length: small values take as many bytes as any other
+        let small = Transcript { c: BigUint::from(1u32), s: BigUint::from(0u32), ..proof.clone() };
+        assert_eq!(zkp.encode_transcript(&small).len(), proof_bytes.len());
+        assert_eq!(proof_bytes.len(), 2 + 4 * 4 + 2 * zkp.group.element_len() + 2 * zkp.group.scalar_len());
+
+        //* Header
+        let mut other_version = proof_bytes.clone();
+        other_version[0] = 2;
+        assert_eq!(zkp.decode_transcript(&other_version), Err(EncodingError::UnsupportedVersion(2)));
+        assert_eq!(zkp.decode_transcript(&statement_bytes), Err(EncodingError::WrongKind(KIND_STATEMENT)));
+        assert_eq!(ZKP::<G>::decode_parameters(&proof_bytes).err(), Some(EncodingError::WrongKind(KIND_TRANSCRIPT)));
+
+        //* Framing
+        assert_eq!(zkp.decode_transcript(&proof_bytes[..proof_bytes.len() - 1]), Err(EncodingError::Truncated));
+        assert_eq!(zkp.decode_transcript(&[]), Err(EncodingError::Truncated));
+        assert_eq!(zkp.decode_statement(&seal(KIND_STATEMENT, &[&zkp.group.encode(&statement.y1)])), Err(EncodingError::Truncated));
+        assert_eq!(zkp.decode_transcript(&[&proof_bytes[..], &[0]].concat()), Err(EncodingError::Truncated));
+        assert_eq!(zkp.decode_transcript(&[&proof_bytes[..], &[0, 0, 0, 0]].concat()), Err(EncodingError::TrailingBytes));
+
+        //* Non-canonical fields: leading zero, s + q
+        let y1 = [&[0u8][..], &zkp.group.encode(&statement.y1)].concat();
+        let padded = seal(KIND_STATEMENT, &[&y1, &zkp.group.encode(&statement.y2)]);
+        assert_eq!(zkp.decode_statement(&padded), Err(EncodingError::Invalid("y1", ValidationError::Malformed)));
+
+        let q = zkp.group.order();
+        let malleated = zkp.encode_transcript(&Transcript { s: &proof.s + q, ..proof.clone() });
+        assert!(matches!(zkp.decode_transcript(&malleated), Err(EncodingError::Invalid("s", _))));
+
+        //* Identity
+        let identity = zkp.pow(&zkp.alpha, &BigUint::from(0u32));
+        let degenerate = zkp.encode_statement(&Statement { y1: identity, ..statement });
+        assert_eq!(zkp.decode_statement(&degenerate), Err(EncodingError::Invalid("y1", ValidationError::Identity)));
+    }
+
+    #[test]
+    fn test_encoding_modp() {
+        check_round_trip(&ZKP::modp());
+    }
+
+    #[test]
+    fn test_encoding_ristretto255() {
+        check_round_trip(&ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_decode_parameters_modp() {
+        use crate::zkp_cp::ModPGroup;
+
+        let zkp = ZKP::modp();
+        let group = &zkp.group;
+        let (p, q, g) = (group.p.to_bytes_be(), group.q.to_bytes_be(), group.g.to_bytes_be());
+        let (alpha, beta) = (group.encode(&zkp.alpha), group.encode(&zkp.beta));
+        let encode = |fields: &[&[u8]]| seal(KIND_PARAMS, fields);
+        let non_canonical = Some(EncodingError::Params(ParamError::Invalid("group parameters are not canonical")));
+
+        //* Registry name; the same numbers without it are another group
+        let named = ZKP::<ModPGroup>::decode_parameters(&encode(&[b"rfc5114-1024-160", &p, &q, &g, &alpha, &beta])).unwrap();
+        assert_eq!(named.group, zkp.group);
+        let unnamed = ZKP::<ModPGroup>::decode_parameters(&encode(&[b"modp-1024", &p, &q, &g, &alpha, &beta])).unwrap();
+        assert_eq!((unnamed.group.name, unnamed.group.name()), (None, "modp-1024".to_string()));
+        //* Leading zero on q; name of another size; registry name of another group
+        assert_eq!(ZKP::<ModPGroup>::decode_parameters(&encode(&[b"modp-1024", &p, &[&[0u8][..], &q].concat(), &g, &alpha, &beta])).err(), non_canonical);
+        assert_eq!(ZKP::<ModPGroup>::decode_parameters(&encode(&[b"modp-2048", &p, &q, &g, &alpha, &beta])).err(), non_canonical);
+        assert_eq!(ZKP::<ModPGroup>::decode_parameters(&encode(&[b"rfc5114-2048-256", &p, &q, &g, &alpha, &beta])).err(), non_canonical);
+        //* beta = alpha
+        assert_eq!(
+            ZKP::<ModPGroup>::decode_parameters(&encode(&[b"modp-1024", &p, &q, &g, &alpha, &alpha])).err(),
+            Some(EncodingError::Params(ParamError::Invalid("alpha = beta")))
+        );
+        //* Parameters of another backend
+        assert!(ZKP::<crate::zkp_ec::Ristretto255>::decode_parameters(&zkp.encode_parameters()).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+// Errors of the protocol and of the server built on it.
+// Every way a request can fail is a ZkpError; each variant has exactly one gRPC status code.
+
+// Status codes (From<ZkpError> for tonic::Status)
+// - InvalidArgument: malformed or out-of-range values, requests breaking a server rule
+// - PermissionDenied: failed (batch) verification, expired challenge or proof, replayed proof
+// - NotFound: unknown user, auth_id or session (logout)
+// - AlreadyExists: username taken
+// - Unauthenticated: invalid or expired session (validate, refresh)
+// - FailedPrecondition: unusable group parameters
+// - Internal: storage failures
+
+use std::{fmt, sync::{Mutex, MutexGuard, PoisonError}};
+use tonic::{Code, Status};
+use crate::store::StoreError;
+use crate::validation::{ParamError, ValidationError};
+
+#[derive(Debug)]
+pub enum ZkpError {
+    Malformed(&'static str), //* Field does not decode.
+    OutOfRange(&'static str, ValidationError), //* Field decodes, but is not acceptable (identity, not in the subgroup, s >= q).
+    Rejected(String), //* Well-formed request refused by a server rule (short salt, KDF below the floor, ...).
+    VerificationFailed,
+    BatchFailed(Vec<usize>), //* Indices of the proofs that do not verify.
+    ChallengeExpired,
+    ProofExpired,
+    ProofReplayed,
+    UnknownUser(String),
+    UserExists(String),
+    UnknownChallenge,
+    InvalidSession, //* Unknown or expired; validate and refresh.
+    UnknownSession, //* Nothing to log out of.
+    Params(ParamError),
+    Storage(StoreError),
+}
+
+impl ZkpError {
+    //* Received field that failed validation; keeps malformed encodings apart from bad values.
+    pub fn invalid(field: &'static str, e: ValidationError) -> ZkpError {
+        match e {
+            ValidationError::Malformed => ZkpError::Malformed(field),
+            _ => ZkpError::OutOfRange(field, e),
+        }
+    }
+
+    pub fn code(&self) -> Code {
+        match self {
+            ZkpError::Malformed(_) | ZkpError::OutOfRange(..) | ZkpError::Rejected(_) => Code::InvalidArgument,
+            ZkpError::VerificationFailed | ZkpError::BatchFailed(_) | ZkpError::ChallengeExpired | ZkpError::ProofExpired | ZkpError::ProofReplayed => Code::PermissionDenied,
+            ZkpError::UnknownUser(_) | ZkpError::UnknownChallenge | ZkpError::UnknownSession => Code::NotFound,
+            ZkpError::UserExists(_) => Code::AlreadyExists,
+            ZkpError::InvalidSession => Code::Unauthenticated,
+            ZkpError::Params(_) => Code::FailedPrecondition,
+            ZkpError::Storage(_) => Code::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ZkpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZkpError::Malformed(field) => write!(f, "{}: {}", field, ValidationError::Malformed),
+            ZkpError::OutOfRange(field, e) => write!(f, "{}: {}", field, e),
+            ZkpError::Rejected(reason) => f.write_str(reason),
+            ZkpError::VerificationFailed => f.write_str("Permission Denied."),
+            ZkpError::BatchFailed(failed) => write!(f, "Permission Denied for proofs {:?}.", failed),
+            ZkpError::ChallengeExpired => f.write_str("Challenge expired."),
+            ZkpError::ProofExpired => f.write_str("Proof expired."),
+            ZkpError::ProofReplayed => f.write_str("Proof already used."),
+            ZkpError::UnknownUser(username) => write!(f, "User: {} not found", username),
+            ZkpError::UserExists(username) => write!(f, "User: {} already exists", username),
+            ZkpError::UnknownChallenge => f.write_str("Invalid Auth Id."),
+            ZkpError::InvalidSession => f.write_str("Invalid or expired session."),
+            ZkpError::UnknownSession => f.write_str("Invalid session."),
+            ZkpError::Params(e) => write!(f, "Group parameters: {}", e),
+            ZkpError::Storage(e) => write!(f, "User storage: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ZkpError {}
+
+impl From<ParamError> for ZkpError {
+    fn from(e: ParamError) -> Self {
+        ZkpError::Params(e)
+    }
+}
+
+impl From<StoreError> for ZkpError {
+    fn from(e: StoreError) -> Self {
+        ZkpError::Storage(e)
+    }
+}
+
+impl From<ZkpError> for Status {
+    fn from(e: ZkpError) -> Self {
+        Status::new(e.code(), e.to_string())
+    }
+}
+
+//* Lock that survives a panic of another holder.
+//* Every critical section is a single map insert / remove, so a poisoned map is still consistent;
+//* giving up on it would take the whole server down with one failed request.
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{collections::HashMap, io, sync::Arc};
+
+    #[test]
+    fn test_status_codes() {
+        let cases = [
+            (ZkpError::invalid("y1", ValidationError::Malformed), Code::InvalidArgument),
+            (ZkpError::invalid("y1", ValidationError::Identity), Code::InvalidArgument),
+            (ZkpError::Rejected("short salt".to_string()), Code::InvalidArgument),
+            (ZkpError::VerificationFailed, Code::PermissionDenied),
+            (ZkpError::BatchFailed(vec![1, 4]), Code::PermissionDenied),
+            (ZkpError::ChallengeExpired, Code::PermissionDenied),
+            (ZkpError::ProofReplayed, Code::PermissionDenied),
+            (ZkpError::UnknownUser("alice".to_string()), Code::NotFound),
+            (ZkpError::UserExists("alice".to_string()), Code::AlreadyExists),
+            (ZkpError::InvalidSession, Code::Unauthenticated),
+            (ParamError::Invalid("p is not prime").into(), Code::FailedPrecondition),
+            (StoreError::Io(io::Error::other("disk full")).into(), Code::Internal),
+        ];
+        for (e, code) in cases {
+            let message = e.to_string();
+            let status = Status::from(e);
+            assert_eq!(status.code(), code);
+            assert_eq!(status.message(), message);
+        }
+
+        assert!(matches!(ZkpError::invalid("s", ValidationError::ScalarOutOfRange), ZkpError::OutOfRange("s", _)));
+        assert_eq!(ZkpError::invalid("r1", ValidationError::Malformed).to_string(), "r1: malformed encoding");
+    }
+
+    #[test]
+    fn test_lock_poisoned() {
+        let map = Arc::new(Mutex::new(HashMap::from([(1u32, 1u32)])));
+
+        //* A thread panics while holding the lock.
+        let poisoner = map.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("handler bug");
+        }).join();
+        assert!(map.is_poisoned());
+
+        lock(&map).insert(2, 2);
+        assert_eq!(lock(&map).len(), 2);
+    }
+}
@@ -0,0 +1,89 @@
+// Fixed-base tables for alpha and beta (ZKP::with_tables).
+// The bases never change; their powers are computed once and commit() / verify() read them
+// instead of running the generic exponentiation.
+
+// - ModPGroup: 4-bit fixed windows (ct::FixedBase); secret exponents read every entry of a window
+// - Ristretto255: basepoint tables of curve25519-dalek
+
+// Tables are found by their base: a ZKP whose alpha or beta was replaced afterwards falls back to pow().
+
+use std::sync::Arc;
+use crate::group::Group;
+use crate::zkp_cp::ZKP;
+
+pub struct Tables<G: Group> {
+    alpha: (G::Element, G::Table),
+    beta: (G::Element, G::Table),
+}
+
+impl<G: Group> ZKP<G> {
+    //* Builds the tables once; clones of the ZKP share them.
+    pub fn with_tables(self) -> Self {
+        let tables = Tables {
+            alpha: (self.alpha.clone(), self.group.precompute(&self.alpha)),
+            beta: (self.beta.clone(), self.group.precompute(&self.beta)),
+        };
+        ZKP { tables: Some(Arc::new(tables)), ..self }
+    }
+
+    //* Table of base, if base is the alpha or beta it was built for.
+    pub(crate) fn table(&self, base: &G::Element) -> Option<&G::Table> {
+        let tables = self.tables.as_deref()?;
+        [&tables.alpha, &tables.beta].into_iter().find(|(b, _)| b == base).map(|(_, table)| table)
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigUint;
+    use crate::zkp_cp::{Group, SecretScalar, ZKP};
+
+    fn check_tables<G: Group>(zkp: ZKP<G>) {
+        let plain = ZKP { tables: None, group: zkp.group.clone(), alpha: zkp.alpha.clone(), beta: zkp.beta.clone() };
+        let fast = zkp.with_tables();
+        assert!(fast.table(&fast.alpha).is_some() && fast.table(&fast.beta).is_some());
+
+        let q = fast.group.order();
+        for exp in [BigUint::from(0u32), BigUint::from(1u32), q - 1u32, fast.random_scalar()] {
+            for base in [&fast.alpha, &fast.beta] {
+                assert_eq!(fast.pow(base, &exp), plain.group.pow(base, &exp));
+            }
+            let secret = SecretScalar::new(exp.clone());
+            assert_eq!(fast.commit(&secret), plain.commit(&secret));
+        }
+
+        //* Exponent >= q
+        assert_eq!(fast.pow(&fast.alpha, &(q * 2u32 + 3u32)), plain.group.pow(&plain.alpha, &BigUint::from(3u32)));
+
+        //* Other bases are not in the table
+        let y = fast.commit(&fast.random_secret()).0;
+        assert!(fast.table(&y).is_none());
+    }
+
+    #[test]
+    fn test_tables_modp() {
+        for name in ZKP::named_groups() {
+            check_tables(ZKP::named(name).unwrap());
+        }
+        check_tables(ZKP::modp());
+    }
+
+    #[test]
+    fn test_tables_ristretto255() {
+        check_tables(ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_stale_tables() {
+        //* beta replaced after the tables were built: beta^x must not come from the old table.
+        let zkp = ZKP::modp().with_tables();
+        let other_beta = zkp.pow(&zkp.alpha, &BigUint::from(5u32));
+        let replaced = ZKP { beta: other_beta.clone(), ..zkp };
+        assert!(replaced.table(&replaced.beta).is_none());
+        assert_eq!(replaced.pow(&replaced.beta, &BigUint::from(7u32)), other_beta.modpow(&BigUint::from(7u32), &replaced.group.p));
+    }
+}
@@ -0,0 +1,340 @@
+// Group abstraction for the Chaum-Pedersen Protocol.
+// ZKP is written once against this trait; each backend only provides the arithmetic.
+
+// Require following functions
+// - generator(), order(), encode_params(): public description of the group
+// - pow(): a^x (x * a for additive groups)
+// - pow_secret(): pow() for a secret x (commitments); constant time in x
+// - mul(): a * b (a + b for additive groups)
+// - multi_pow(): prod a_i^x_i in one pass (verify, batch verification); public exponents only
+// - precompute()/pow_table()/pow_table_secret(): fixed-base tables for alpha and beta (ZKP::with_tables)
+// - encode()/decode(): wire format of the elements; fixed length (element_len), decode() rejects anything else
+// - decode_params(): inverse of encode_params() (encoding.rs)
+// - check_element(): membership of received elements (validation.rs)
+// - check_params(): the group itself is well formed (parameters received from a server)
+// - hash_to_group(): element with unknown discrete log, derived from a public seed (beta)
+// - beta_seed(): that seed, for the groups that fix one (named groups, ristretto255)
+
+// Scalars (c, s) are BigUint in [0, q); secret exponents (x, k) are SecretScalar, never copied into a BigUint.
+
+use num_bigint::{BigUint, RandBigInt};
+use std::fmt::Debug;
+use crate::{constant, ct, multiexp, params};
+use crate::secret::SecretScalar;
+use crate::validation::{ParamError, ValidationError};
+
+//* Miller-Rabin rounds; error probability <= 4^-40 = 2^-80.
+pub const PRIMALITY_ROUNDS: usize = 40;
+
+pub trait Group: Clone + PartialEq + Debug + Send + Sync + 'static {
+    type Element: Clone + PartialEq + Debug + Send + Sync;
+    type Table: Send + Sync;
+
+    //* Identifier of the group; shared between the server and the client.
+    fn name(&self) -> String;
+
+    //* q; order of the group generated by generator().
+    fn order(&self) -> &BigUint;
+
+    fn generator(&self) -> Self::Element;
+
+    //* p; modulus of the field the elements are built on (2^255 - 19 for ristretto255).
+    fn modulus(&self) -> BigUint;
+
+    //* Public description of the group; bound into Fiat-Shamir challenges.
+    fn encode_params(&self) -> Vec<Vec<u8>> {
+        vec![self.name().into_bytes(), self.order().to_bytes_be()]
+    }
+
+    //* The group encode_params() describes; None for any other bytes.
+    fn decode_params(fields: &[Vec<u8>]) -> Option<Self>;
+
+    //* output = n^exp
+    fn pow(&self, n: &Self::Element, exp: &BigUint) -> Self::Element;
+
+    //* output = n^exp; time does not depend on exp (secret x, nonce k).
+    fn pow_secret(&self, n: &Self::Element, exp: &SecretScalar) -> Self::Element;
+
+    //* output = a * b
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+
+    //* Powers of base, built once; base^exp from it without the generic exponentiation.
+    fn precompute(&self, base: &Self::Element) -> Self::Table;
+
+    fn pow_table(&self, table: &Self::Table, exp: &BigUint) -> Self::Element;
+
+    //* Same as pow_table(); time does not depend on exp.
+    fn pow_table_secret(&self, table: &Self::Table, exp: &SecretScalar) -> Self::Element;
+
+    //* output = prod n_i^exp_i; the identity for no terms. Variable time.
+    fn multi_pow(&self, terms: &[(&Self::Element, &BigUint)]) -> Self::Element {
+        let identity = self.pow(&self.generator(), &BigUint::from(0u32));
+        terms.iter().fold(identity, |acc, (n, exp)| self.mul(&acc, &self.pow(n, exp)))
+    }
+
+    //* Byte length of every encoded element.
+    fn element_len(&self) -> usize;
+
+    fn encode(&self, e: &Self::Element) -> Vec<u8>;
+
+    //* None if the bytes are not the encoding of an element; one encoding per element.
+    fn decode(&self, bytes: &[u8]) -> Option<Self::Element>;
+
+    //* Ok if e is a non-identity element of the order-q group.
+    fn check_element(&self, e: &Self::Element) -> Result<(), ValidationError>;
+
+    //* Ok if q is prime and generator() has order q.
+    fn check_params(&self) -> Result<(), ParamError>;
+
+    //* Element of order q hashed from seed; its discrete log is unknown to everyone.
+    fn hash_to_group(&self, seed: &[u8]) -> Option<Self::Element>;
+
+    //* Seed of the standard beta of this group; None if the group does not fix one (generated groups carry their own).
+    fn beta_seed(&self) -> Option<Vec<u8>>;
+
+    //* Scalars are big-endian, padded to the byte length of q.
+    fn scalar_len(&self) -> usize {
+        self.order().bits().div_ceil(8) as usize
+    }
+
+    fn encode_scalar(&self, s: &BigUint) -> Vec<u8> {
+        let bytes = s.to_bytes_be();
+        let mut out = vec![0u8; self.scalar_len().saturating_sub(bytes.len())];
+        out.extend_from_slice(&bytes);
+        out
+    }
+
+    //* None for any other length; the range (< q) is checked by parse_scalar.
+    fn decode_scalar(&self, bytes: &[u8]) -> Option<BigUint> {
+        (bytes.len() == self.scalar_len()).then(|| BigUint::from_bytes_be(bytes))
+    }
+}
+
+//* Multiplicative group of integers mod p; subgroup of prime order q generated by g.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModPGroup {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+    pub name: Option<&'static str>, //* Registry name (constant::NAMED_GROUPS); None for any other group.
+}
+
+impl ModPGroup {
+    //* Group of the registry (constant::NAMED_GROUPS), named after it.
+    pub fn named(name: &str) -> Option<ModPGroup> {
+        let (p, g, q) = constant::named_group(name)?;
+        let name = constant::NAMED_GROUPS.iter().map(|group| group.name).find(|n| *n == name)?;
+        Some(ModPGroup { p: BigUint::from_bytes_be(&p), q: BigUint::from_bytes_be(&q), g: BigUint::from_bytes_be(&g), name: Some(name) })
+    }
+}
+
+impl Group for ModPGroup {
+    type Element = BigUint;
+    type Table = ct::FixedBase;
+
+    //* Registry name (e.g. "rfc5114-2048-256"), or "modp-<bits of p>" for other groups.
+    fn name(&self) -> String {
+        match self.name {
+            Some(name) => name.to_string(),
+            None => format!("modp-{}", self.p.bits()),
+        }
+    }
+
+    fn order(&self) -> &BigUint {
+        &self.q
+    }
+
+    fn generator(&self) -> BigUint {
+        self.g.clone()
+    }
+
+    fn modulus(&self) -> BigUint {
+        self.p.clone()
+    }
+
+    fn encode_params(&self) -> Vec<Vec<u8>> {
+        vec![self.name().into_bytes(), self.p.to_bytes_be(), self.q.to_bytes_be(), self.g.to_bytes_be()]
+    }
+
+    //* Minimal big-endian p, q, g (no leading zeros); a registry name only with the registry's p, q, g,
+    //* any other name must be "modp-<bits of p>".
+    fn decode_params(fields: &[Vec<u8>]) -> Option<Self> {
+        let [name, p, q, g] = fields else { return None };
+        let group = match std::str::from_utf8(name).ok().and_then(ModPGroup::named) {
+            Some(named) => named,
+            None => ModPGroup { p: BigUint::from_bytes_be(p), q: BigUint::from_bytes_be(q), g: BigUint::from_bytes_be(g), name: None },
+        };
+        (group.encode_params() == fields).then_some(group)
+    }
+
+    fn pow(&self, n: &BigUint, exp: &BigUint) -> BigUint {
+        n.modpow(exp, &self.p)
+    }
+
+    fn pow_secret(&self, n: &BigUint, exp: &SecretScalar) -> BigUint {
+        ct::pow_mod(n, exp, &self.p, &self.q)
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.p
+    }
+
+    fn precompute(&self, base: &BigUint) -> ct::FixedBase {
+        ct::FixedBase::new(base, &self.p, &self.q)
+    }
+
+    fn pow_table(&self, table: &ct::FixedBase, exp: &BigUint) -> BigUint {
+        table.pow(exp)
+    }
+
+    fn pow_table_secret(&self, table: &ct::FixedBase, exp: &SecretScalar) -> BigUint {
+        table.pow_secret(exp)
+    }
+
+    //* Straus, multiexp.rs
+    fn multi_pow(&self, terms: &[(&BigUint, &BigUint)]) -> BigUint {
+        multiexp::multi_pow(terms, &self.p)
+    }
+
+    //* Byte length of p
+    fn element_len(&self) -> usize {
+        self.p.bits().div_ceil(8) as usize
+    }
+
+    //* Big-endian, padded to element_len()
+    fn encode(&self, e: &BigUint) -> Vec<u8> {
+        let bytes = e.to_bytes_be();
+        let mut out = vec![0u8; self.element_len().saturating_sub(bytes.len())];
+        out.extend_from_slice(&bytes);
+        out
+    }
+
+    //* Exactly element_len() bytes, value < p
+    fn decode(&self, bytes: &[u8]) -> Option<BigUint> {
+        if bytes.len() != self.element_len() {
+            return None;
+        }
+        let e = BigUint::from_bytes_be(bytes);
+        (e < self.p).then_some(e)
+    }
+
+    //* 1 < e < p and e^q = 1 mod p
+    fn check_element(&self, e: &BigUint) -> Result<(), ValidationError> {
+        if *e == BigUint::from(1u32) {
+            return Err(ValidationError::Identity);
+        }
+        if *e == BigUint::from(0u32) || *e >= self.p {
+            return Err(ValidationError::OutOfRange);
+        }
+        if e.modpow(&self.q, &self.p) != BigUint::from(1u32) {
+            return Err(ValidationError::NotInSubgroup);
+        }
+        Ok(())
+    }
+
+    //* p, q prime; q | p - 1; g of order q
+    fn check_params(&self) -> Result<(), ParamError> {
+        if self.p.bits() > ct::MAX_BITS {
+            return Err(ParamError::Invalid("p is wider than 8192 bits"));
+        }
+        if !is_probable_prime(&self.p, PRIMALITY_ROUNDS) {
+            return Err(ParamError::Invalid("p is not prime"));
+        }
+        if !is_probable_prime(&self.q, PRIMALITY_ROUNDS) {
+            return Err(ParamError::Invalid("q is not prime"));
+        }
+        if (&self.p - 1u32) % &self.q != BigUint::from(0u32) {
+            return Err(ParamError::Invalid("q does not divide p - 1"));
+        }
+        self.check_element(&self.g).map_err(|_| ParamError::Invalid("g is not a generator of the order-q subgroup"))
+    }
+
+    //* FIPS 186-4 A.2.3 (params.rs)
+    fn hash_to_group(&self, seed: &[u8]) -> Option<BigUint> {
+        params::derive_generator(&self.p, &self.q, seed, params::BETA_INDEX)
+    }
+
+    //* params::beta_seed(registry name)
+    fn beta_seed(&self) -> Option<Vec<u8>> {
+        self.name.map(params::beta_seed)
+    }
+}
+
+//* Miller-Rabin with random bases.
+pub fn is_probable_prime(n: &BigUint, rounds: usize) -> bool {
+    let one = BigUint::from(1u32);
+    let two = BigUint::from(2u32);
+    if *n < two {
+        return false;
+    }
+    for small in [2u32, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37] {
+        if *n == BigUint::from(small) {
+            return true;
+        }
+        if n % small == BigUint::from(0u32) {
+            return false;
+        }
+    }
+
+    //* n - 1 = d * 2^r, d odd
+    let n_minus_one = n - 1u32;
+    let r = n_minus_one.trailing_zeros().unwrap_or(0); //* n - 1 > 0 here
+    let d = &n_minus_one >> r;
+
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..r {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_probable_prime() {
+        let primes: Vec<u32> = (0..200u32).filter(|&n| n > 1 && (2..n).all(|d| n % d != 0)).collect();
+        for n in 0..200u32 {
+            assert_eq!(is_probable_prime(&BigUint::from(n), 16), primes.contains(&n), "{}", n);
+        }
+
+        //* Carmichael numbers fool Fermat, not Miller-Rabin.
+        for n in [561u32, 1105, 1729, 2465, 2821, 6601, 8911] {
+            assert!(!is_probable_prime(&BigUint::from(n), 16));
+        }
+
+        //* 2^127 - 1 (Mersenne prime) and 2^128 + 1 (= 59649589127497217 * 5704689200685129054721)
+        let m127 = (BigUint::from(1u32) << 127) - 1u32;
+        assert!(is_probable_prime(&m127, PRIMALITY_ROUNDS));
+        assert!(!is_probable_prime(&((BigUint::from(1u32) << 128) + 1u32), PRIMALITY_ROUNDS));
+    }
+
+    #[test]
+    fn test_check_params_modp() {
+        let group = |p: u32, q: u32, g: u32| ModPGroup { p: BigUint::from(p), q: BigUint::from(q), g: BigUint::from(g), name: None };
+
+        assert_eq!(group(23, 11, 4).check_params(), Ok(()));
+        assert_eq!(group(25, 11, 4).check_params(), Err(ParamError::Invalid("p is not prime")));
+        assert_eq!(group(23, 9, 4).check_params(), Err(ParamError::Invalid("q is not prime")));
+        assert_eq!(group(23, 7, 4).check_params(), Err(ParamError::Invalid("q does not divide p - 1")));
+        //* 5 generates the whole group (order 22); 1 is the identity
+        assert!(group(23, 11, 5).check_params().is_err());
+        assert!(group(23, 11, 1).check_params().is_err());
+    }
+}
@@ -0,0 +1,158 @@
+// Password -> secret x in Z_q (Key Derivation Function).
+// x = Argon2id(password, salt, params) mod q
+
+// - salt: random, per user; stored by the server and served at login time.
+// - params: versioned cost parameters; stored per user so that they can be raised later
+//   without breaking users registered with the old ones.
+
+use std::fmt;
+use argon2::{Algorithm, Argon2, Params, Version};
+use num_bigint::BigUint;
+use rand::RngCore;
+use zeroize::Zeroizing;
+use crate::ct;
+use crate::secret::SecretScalar;
+
+pub const SALT_LEN: usize = 16;
+
+//* 512 bits; reduction mod q has negligible bias for any q we use.
+const OUTPUT_LEN: usize = 64;
+
+//* Version 1: Argon2id (v0x13), 64-byte output reduced mod q.
+pub const KDF_V1: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub version: u32,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KdfError {
+    UnsupportedVersion(u32),
+    Argon2(argon2::Error),
+}
+
+impl fmt::Display for KdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KdfError::UnsupportedVersion(version) => write!(f, "unsupported KDF version {}", version),
+            KdfError::Argon2(e) => write!(f, "argon2: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for KdfError {}
+
+//* OWASP recommendation for Argon2id: 19 MiB, 2 iterations, 1 lane.
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            version: KDF_V1,
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KdfParams {
+    //* Same version and no cost below the floor.
+    pub fn meets(&self, floor: &KdfParams) -> bool {
+        self.version == floor.version
+            && self.memory_kib >= floor.memory_kib
+            && self.iterations >= floor.iterations
+            && self.parallelism >= floor.parallelism
+    }
+
+    //* version || memory_kib || iterations || parallelism, 4 bytes big-endian each
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.version, self.memory_kib, self.iterations, self.parallelism]
+            .iter()
+            .flat_map(|n| n.to_be_bytes())
+            .collect()
+    }
+
+    //* derive_secret
+    // output = x = Argon2id(password, salt) mod q
+    pub fn derive_secret(&self, password: &[u8], salt: &[u8], q: &BigUint) -> Result<SecretScalar, KdfError> {
+        if self.version != KDF_V1 {
+            return Err(KdfError::UnsupportedVersion(self.version));
+        }
+
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(OUTPUT_LEN))
+            .map_err(KdfError::Argon2)?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+        let mut out = Zeroizing::new([0u8; OUTPUT_LEN]);
+        argon2.hash_password_into(password, salt, out.as_mut()).map_err(KdfError::Argon2)?;
+
+        //* Big-endian output; reduced without a BigUint copy (ct::reduce).
+        let mut wide = Zeroizing::new(out.to_vec());
+        wide.reverse();
+        Ok(ct::reduce(&wide, q))
+    }
+}
+
+pub fn gen_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    //* Cheap parameters; the defaults take too long for unit tests.
+    fn cheap() -> KdfParams {
+        KdfParams { version: KDF_V1, memory_kib: 64, iterations: 1, parallelism: 1 }
+    }
+
+    #[test]
+    fn test_derive_secret() {
+        let q = BigUint::from_bytes_be(&hex::decode("F518AA8781A8DF278ABA4E7D64B7CB9D49462353").unwrap());
+        let salt = gen_salt();
+
+        let secret = cheap().derive_secret(b"password", &salt, &q).unwrap();
+        let x = secret.expose();
+        assert!(x < q);
+
+        //* Deterministic for the same inputs
+        assert_eq!(x, cheap().derive_secret(b"password", &salt, &q).unwrap().expose());
+
+        //* Same password, different salt -> different secret
+        let other_salt = gen_salt();
+        assert_ne!(x, cheap().derive_secret(b"password", &other_salt, &q).unwrap().expose());
+
+        //* Cost parameters are part of the derivation
+        let costly = KdfParams { iterations: 2, ..cheap() };
+        assert_ne!(x, costly.derive_secret(b"password", &salt, &q).unwrap().expose());
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let q = BigUint::from(11u32);
+
+        let unknown = KdfParams { version: 2, ..cheap() };
+        assert_eq!(unknown.derive_secret(b"pw", &gen_salt(), &q).unwrap_err(), KdfError::UnsupportedVersion(2));
+
+        //* Salt shorter than Argon2 allows
+        assert!(cheap().derive_secret(b"pw", b"short", &q).is_err());
+    }
+
+    #[test]
+    fn test_meets() {
+        let floor = KdfParams::default();
+        assert!(floor.meets(&floor));
+        assert!(KdfParams { iterations: 3, ..floor }.meets(&floor));
+        assert!(!cheap().meets(&floor));
+        assert!(!KdfParams { version: 2, ..floor }.meets(&floor));
+    }
+}
@@ -3,9 +3,19 @@
 // Protocol: Chaum-Pedersen Protocol
 
 // Require following functions
-// - pow(): a^x mod P
-// - solve(): generate s
-// - verify(): verify s
+// - pow(): a^x in the group (a^x mod P for zkp_cp)
+// - solve(): generate s (constant time in k and x, ct.rs)
+// - SecretScalar: x, k and s; wiped on drop, redacted in Debug (secret.rs)
+// - nonce(): k from HMAC(x, context) and fresh entropy (nonce.rs)
+// - with_tables(): precomputed powers of alpha and beta for commit() and verify() (fixed_base.rs)
+// - multi_pow(): a^s * b^c in a single pass, Shamir's trick / Straus (multiexp.rs)
+// - verify(): verify s; Err(ZkpError::VerificationFailed) on a wrong s (error.rs)
+// - verify_batch(): N proofs at once, bisects to the bad ones (batch.rs)
+// - transcript()/challenge(): Merlin transcript bound to flow, group, username and server; every challenge (transcript.rs)
+// - prove_noninteractive()/verify_noninteractive(): Fiat-Shamir variant (noninteractive.rs)
+// - parse_element()/parse_scalar(): validation of received values (validation.rs)
+// - encode_statement()/decode_transcript()/...: canonical, versioned byte encodings (encoding.rs)
+// - Serialize/Deserialize: canonical encodings as hex JSON, CBOR; feature "serde" (serialize.rs)
 
 
 // Require following module
@@ -13,62 +23,157 @@
 // - Big Integers
 
 
+mod batch;
 mod constant;
+pub mod ct;
+mod encoding;
+pub mod error;
+mod fixed_base;
+pub mod group;
+pub mod kdf;
+mod multiexp;
+mod noninteractive;
+mod nonce;
+pub mod params;
+pub mod secret;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod session;
+pub mod store;
+mod transcript;
+mod validation;
+pub mod zkp_ec;
+
+//* Wire protocol version; served by GetParameters, checked by the client.
+//* 2: challenges from transcripts (transcript.rs); interactive challenges carry the verifier nonce.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+//* Group backend selection; shared between the server and the client.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Backend {
+    #[default]
+    ModP, //* 1024-bit prime field (zkp_cp)
+    Ristretto255, //* elliptic curve (zkp_ec)
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "modp" => Some(Backend::ModP),
+            "ristretto255" => Some(Backend::Ristretto255),
+            _ if zkp_cp::ZKP::named_groups().contains(&name) => Some(Backend::ModP),
+            _ if name.starts_with("file:") => Some(Backend::ModP), //* paramgen output
+            _ => None,
+        }
+    }
+
+    //* Backend of a group name (Group::name(), e.g. "rfc5114-2048-256" or "modp-1024").
+    pub fn of_group(group: &str) -> Option<Backend> {
+        match group {
+            "ristretto255" => Some(Backend::Ristretto255),
+            _ if zkp_cp::ZKP::named_groups().contains(&group) => Some(Backend::ModP),
+            _ if group.starts_with("modp-") => Some(Backend::ModP),
+            _ => None,
+        }
+    }
+}
 
 //* module zkp chaum-pedersen
 pub mod zkp_cp {
+    use std::sync::Arc;
     use num_bigint::{BigUint, RandBigInt};
-    use rand;
-    use rand::Rng;
-    use crate::constant;
-
-    pub struct  ZKP {
-        pub p: BigUint,
-        pub q: BigUint,
-        pub alpha: BigUint,
-        pub beta: BigUint,
+    use rand::{Rng, RngCore};
+    use zeroize::Zeroizing;
+    use crate::{constant, ct, params};
+    pub use crate::group::{Group, ModPGroup};
+    pub use crate::params::{generate, GroupParams, PocklingtonStep, PrimeCertificate};
+    pub use crate::nonce::NONCE_DOMAIN;
+    pub use crate::noninteractive::Proof;
+    pub use crate::transcript::{verifier_nonce, Flow, ProtocolTranscript, PROTOCOL_LABEL, VERIFIER_NONCE_LEN};
+    pub use crate::validation::{ParamError, ValidationError};
+    pub use crate::batch::{BatchEntry, WEIGHT_BITS};
+    pub use crate::encoding::{EncodingError, Statement, Transcript, ENCODING_VERSION};
+    pub use crate::error::ZkpError;
+    pub use crate::fixed_base::Tables;
+    pub use crate::secret::SecretScalar;
+    #[cfg(feature = "serde")]
+    pub use crate::serialize::{ProofRepr, SerdeRepr, StatementRepr, TranscriptRepr};
+
+    //* Default group keeps the original mod p protocol as ZKP.
+    pub struct ZKP<G: Group = ModPGroup> {
+        pub group: G,
+        pub alpha: G::Element,
+        pub beta: G::Element,
+        pub tables: Option<Arc<Tables<G>>>, //* Fixed-base tables of alpha and beta (with_tables).
     }
 
-    impl ZKP {
+    impl<G: Group> ZKP<G> {
         //* pow
-        // a^x mod b
-        // output = n^exp mod modulus
-        pub fn pow(n: &BigUint, exp: &BigUint, modulus: &BigUint) -> BigUint {
-            n.modpow(exp, modulus)
+        // output = n^exp
+        pub fn pow(&self, n: &G::Element, exp: &BigUint) -> G::Element {
+            match self.table(n) {
+                Some(table) => self.group.pow_table(table, exp),
+                None => self.group.pow(n, exp),
+            }
+        }
+
+        fn pow_secret(&self, n: &G::Element, exp: &SecretScalar) -> G::Element {
+            match self.table(n) {
+                Some(table) => self.group.pow_table_secret(table, exp),
+                None => self.group.pow_secret(n, exp),
+            }
+        }
+
+        //* commit
+        // output = (alpha^x, beta^x); (y1, y2) for a secret, (r1, r2) for a nonce.
+        pub fn commit(&self, x: &SecretScalar) -> (G::Element, G::Element) {
+            (self.pow_secret(&self.alpha, x), self.pow_secret(&self.beta, x))
         }
 
         //* solve
         // output = s = k - c * x mod q
-        pub fn solve(&self, k: &BigUint, c: &BigUint, x: &BigUint) -> BigUint {
-            //* If k is less than c*x, it will occur downflow.
-            return if *k >= c * x {
-                (k - c * x).modpow(&BigUint::from(1u32), &self.q)
-            } else {
-                &self.q - (c * x - k).modpow(&BigUint::from(1u32), &self.q)
-            }
+        pub fn solve(&self, k: &SecretScalar, c: &BigUint, x: &SecretScalar) -> SecretScalar {
+            ct::sub_mul_mod(k, c, x, self.group.order())
         }
 
         //* Verify
-// cond1: r1 = a^s * y1^c
-// cond2: r2 - b^s * y2^c
+        // cond1: r1 = a^s * y1^c
+        // cond2: r2 = b^s * y2^c
         pub fn verify(
             &self,
-            r1: &BigUint,
-            r2: &BigUint,
-            y1: &BigUint,
-            y2: &BigUint,
+            r1: &G::Element,
+            r2: &G::Element,
+            y1: &G::Element,
+            y2: &G::Element,
             c: &BigUint,
             s: &BigUint,
-        ) -> bool {
-            let cond1: bool = *r1 == (&self.alpha.modpow(s, &self.p) * y1.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        ) -> Result<(), ZkpError> {
+            let cond1: bool = *r1 == self.multi_pow(&[(&self.alpha, s), (y1, c)]);
+
+            let cond2: bool = *r2 == self.multi_pow(&[(&self.beta, s), (y2, c)]);
+
+            if cond1 && cond2 {
+                Ok(())
+            } else {
+                Err(ZkpError::VerificationFailed)
+            }
+        }
 
-            let cond2: bool = *r2 == (&self.beta.modpow(s, &self.p) * y2.modpow(c, &self.p))
-                .modpow(&BigUint::from(1u32), &self.p);
+        //* Random scalar in [0, q)
+        pub fn random_scalar(&self) -> BigUint {
+            ZKP::gen_rand(self.group.order())
+        }
 
-            cond1 && cond2
+        //* Random scalar in [0, q) for a secret; nonces come from nonce() (nonce.rs)
+        //* Drawn into a wiped buffer with 128 extra bits, so that the reduction has negligible bias.
+        pub fn random_secret(&self) -> SecretScalar {
+            let mut bytes = Zeroizing::new(vec![0u8; self.group.scalar_len() + 16]);
+            rand::thread_rng().fill_bytes(&mut bytes);
+            ct::reduce(&bytes, self.group.order())
         }
+    }
 
+    impl ZKP {
         pub fn gen_rand(limit: &BigUint) -> BigUint {
             let mut rng = rand::thread_rng(); //* Generate random number
 
@@ -84,21 +189,56 @@ pub mod zkp_cp {
         }
 
         pub fn get_const() -> (BigUint, BigUint, BigUint, BigUint) {
-            let (P,G,Q) = constant::gen_large_prime();
-            let p: BigUint = BigUint::from_bytes_be(&P);
-            let q: BigUint = BigUint::from_bytes_be(&Q);
+            let (p_bytes, g_bytes, q_bytes) = constant::gen_large_prime();
+            let p: BigUint = BigUint::from_bytes_be(&p_bytes);
+            let q: BigUint = BigUint::from_bytes_be(&q_bytes);
 
             //* Public
-            let alpha: BigUint = BigUint::from_bytes_be(&G);
-            //* alpha^x is also will be a generator -> define as an beta
-            //* X as an constant
-            let exp: BigUint = BigUint::from_bytes_be(
-                &hex::decode("5d8de022157b32099dc1").unwrap()
-            );
-            let beta: BigUint = alpha.modpow(&exp , &p);
+            let alpha: BigUint = BigUint::from_bytes_be(&g_bytes);
+            //* beta is hashed into the subgroup instead of alpha^x; x would be a trapdoor for whoever knows it.
+            //* Same group and seed as the named group rfc5114-1024-160 (derived once, constant::BETA_1024).
+            let beta: BigUint = BigUint::from_bytes_be(&constant::BETA_1024);
 
             (alpha, beta, p, q)
         }
+
+        //* 1024-bit group with the constants above; named rfc5114-1024-160, which it is.
+        pub fn modp() -> ZKP {
+            let (alpha, beta, p, q) = ZKP::get_const();
+
+            ZKP {
+                group: ModPGroup { p, q, g: alpha.clone(), name: Some("rfc5114-1024-160") },
+                alpha,
+                beta,
+                tables: None,
+            }
+        }
+
+        //* Named group (constant::NAMED_GROUPS); beta is derived from the seed of its name.
+        //* Group::name() is the registry name.
+        pub fn named(name: &str) -> Option<ZKP> {
+            let group = ModPGroup::named(name)?;
+            let alpha = group.g.clone();
+            let beta = group.hash_to_group(&params::beta_seed(name))?;
+
+            Some(ZKP { group, alpha, beta, tables: None })
+        }
+
+        pub fn named_groups() -> Vec<&'static str> {
+            constant::NAMED_GROUPS.iter().map(|group| group.name).collect()
+        }
+
+        //* Group received from elsewhere (e.g. GetParameters); checked before use.
+        pub fn from_modp(p: BigUint, q: BigUint, alpha: BigUint, beta: BigUint) -> Result<ZKP, ParamError> {
+            let zkp = ZKP {
+                group: ModPGroup { p, q, g: alpha.clone(), name: None },
+                alpha,
+                beta,
+                tables: None,
+            };
+            zkp.check_params()?;
+            Ok(zkp)
+        }
     }
 }
 
@@ -109,60 +249,178 @@ pub mod zkp_cp {
 #[cfg(test)]
 mod test {
     use super::*; //* import all
-    use num_bigint::{BigUint, RandBigInt};
-    use rand;
-    use zkp_cp::ZKP;
-    use constant;
+    use num_bigint::BigUint;
+    use zkp_cp::{Group, ModPGroup, SecretScalar, ZkpError, ZKP};
+
+    //* Tiny group: p = 23, q = 11, alpha = 4, beta = 9
+    fn tiny() -> ZKP {
+        ZKP {
+            group: ModPGroup {
+                p: BigUint::from(23u32),
+                q: BigUint::from(11u32),
+                g: BigUint::from(4u32),
+                name: None,
+            },
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+            tables: None,
+        }
+    }
+
+    //* Random scalar in [1, q); zero would make the negative checks pass by accident.
+    fn nonzero_scalar<G: Group>(zkp: &ZKP<G>) -> BigUint {
+        ZKP::gen_rand(&(zkp.group.order() - 1u32)) + 1u32
+    }
+
+    //* Shared suite; every group backend has to pass this.
+    fn check_protocol<G: Group>(zkp: &ZKP<G>) {
+        //* Secrets
+        let x = SecretScalar::new(nonzero_scalar(zkp));
+        let k = zkp.random_secret();
+
+        //* Challenge
+        let c: BigUint = nonzero_scalar(zkp);
+
+        //* Prover
+        let (y1, y2) = zkp.commit(&x);
+        let (r1, r2) = zkp.commit(&k);
+
+        //* Solve
+        let s = zkp.solve(&k, &c, &x);
+        assert!(s.expose() < *zkp.group.order());
+
+        //* Verify
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s.expose()).is_ok());
+
+        //* Encoding round trip
+        let y1_decoded = zkp.group.decode(&zkp.group.encode(&y1)).unwrap();
+        let s_decoded = zkp.group.decode_scalar(&zkp.group.encode_scalar(&s.expose())).unwrap();
+        assert!(zkp.verify(&r1, &r2, &y1_decoded, &y2, &c, &s_decoded).is_ok());
+
+        //* Eavesdropped key
+        let eaves_x = SecretScalar::new((x.expose() + 1u32) % zkp.group.order());
+        let eaves_s = zkp.solve(&k, &c, &eaves_x);
+        assert!(matches!(zkp.verify(&r1, &r2, &y1, &y2, &c, &eaves_s.expose()), Err(ZkpError::VerificationFailed)));
+
+        //* Wrong challenge
+        let eaves_c: BigUint = (&c + 1u32) % zkp.group.order();
+        assert!(zkp.verify(&r1, &r2, &y1, &y2, &eaves_c, &s.expose()).is_err());
+
+        //* Secrets not shared between y1 and y2
+        let (_, other_y2) = zkp.commit(&eaves_x);
+        assert!(zkp.verify(&r1, &r2, &y1, &other_y2, &c, &s.expose()).is_err());
+    }
+
+    #[test]
+    fn test_protocol_tiny() {
+        //* Small enough to exhaust every secret, nonce and challenge.
+        let zkp = tiny();
+        for x in 0..11u32 {
+            for k in 0..11u32 {
+                for c in 0..11u32 {
+                    let (x, k, c) = (SecretScalar::from(BigUint::from(x)), SecretScalar::from(BigUint::from(k)), BigUint::from(c));
+                    let (y1, y2) = zkp.commit(&x);
+                    let (r1, r2) = zkp.commit(&k);
+                    let s = zkp.solve(&k, &c, &x);
+                    assert!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s.expose()).is_ok());
+                }
+            }
+        }
+        check_protocol(&zkp);
+    }
+
+    #[test]
+    fn test_protocol_modp() {
+        check_protocol(&ZKP::modp());
+    }
+
+    #[test]
+    fn test_protocol_ristretto255() {
+        check_protocol(&ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_named_groups() {
+        for name in ZKP::named_groups() {
+            let zkp = ZKP::named(name).unwrap();
+            //* Primes, q | p - 1, alpha and beta of order q
+            assert_eq!(zkp.check_params(), Ok(()), "{}", name);
+            //* The registry name is the identifier; it reaches clients and transcripts.
+            assert_eq!(zkp.group.name(), name);
+            assert!(zkp.check_beta(&zkp.group.beta_seed().unwrap()));
+            assert_eq!(Backend::of_group(name), Some(Backend::ModP));
+            check_protocol(&zkp);
+        }
+
+        //* Advertised sizes
+        let bits = |name: &str| {
+            let zkp = ZKP::named(name).unwrap();
+            (zkp.group.p.bits(), zkp.group.q.bits())
+        };
+        assert_eq!(bits("rfc5114-2048-224"), (2048, 224));
+        assert_eq!(bits("rfc5114-2048-256"), (2048, 256));
+        assert_eq!(bits("rfc3526-3072"), (3072, 3071));
+
+        //* Same group and beta as the original constants
+        let legacy = ZKP::named("rfc5114-1024-160").unwrap();
+        assert_eq!(legacy.group, ZKP::modp().group);
+        assert_eq!(legacy.beta, ZKP::modp().beta);
+        assert!(ZKP::modp().check_beta(&ZKP::modp().group.beta_seed().unwrap()));
+        assert!(ZKP::named("rfc5114-512").is_none());
+        assert_eq!(Backend::from_name("rfc3526-2048"), Some(Backend::ModP));
+        assert_eq!(Backend::from_name("file:group.txt"), Some(Backend::ModP));
+    }
+
     #[test]
     fn test_1024bits() {
         //* Init
-        let (P,G,Q) = constant::gen_large_prime();
+        let (p_bytes, g_bytes, q_bytes) = constant::gen_large_prime();
 
-        let p: BigUint = BigUint::from_bytes_be(&P);
-        let q: BigUint = BigUint::from_bytes_be(&Q);
+        let p: BigUint = BigUint::from_bytes_be(&p_bytes);
+        let q: BigUint = BigUint::from_bytes_be(&q_bytes);
 
         //* Public
-        let alpha: BigUint = BigUint::from_bytes_be(&G);
+        let alpha: BigUint = BigUint::from_bytes_be(&g_bytes);
         //* alpha^x is also will be a generator -> define as an beta
         let beta: BigUint = alpha.modpow(&ZKP::gen_rand(&q), &p);
 
         //* ZKP Protocol Structure
         let zkp:ZKP = ZKP{
-            p: p.clone(),
-            q: q.clone(),
+            group: ModPGroup { p: p.clone(), q: q.clone(), g: alpha.clone(), name: None },
             alpha: alpha.clone(),
-            beta: beta.clone()
+            beta: beta.clone(),
+            tables: None,
         };
 
         //* Secrets
-        let x: BigUint = ZKP::gen_rand(&q);
-        let k: BigUint = ZKP::gen_rand(&q);
+        let x = SecretScalar::new(ZKP::gen_rand(&q));
+        let k = SecretScalar::new(ZKP::gen_rand(&q));
 
         //* Challenge
         let c: BigUint = ZKP::gen_rand(&q);
 
         //* Prover
-        let y1: BigUint = ZKP::pow(&alpha, &x, &p); //* This will be computed as 2
-        let y2: BigUint = ZKP::pow(&beta, &x, &p); //* This will be computed as 3
+        let y1: BigUint = zkp.pow(&alpha, &x.expose());
+        let y2: BigUint = zkp.pow(&beta, &x.expose());
 
         //* Verifier
-        let r1: BigUint = ZKP::pow(&alpha, &k, &p); //* This will be computed as 8
-        let r2: BigUint = ZKP::pow(&beta, &k, &p); //* This will be computed as 4
+        let r1: BigUint = zkp.pow(&alpha, &k.expose());
+        let r2: BigUint = zkp.pow(&beta, &k.expose());
 
         //* Solve
-        let s: BigUint = zkp.solve(&k, &c, &x); //* This will be computed as 5
+        let s = zkp.solve(&k, &c, &x);
 
         //* Assertion
         //* Verify
-        let res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &s); //* This will be computed as true.
+        let res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &s.expose()).is_ok(); //* This will be computed as true.
 
         //* Assertion
         assert!(res); //* Assert to be true
 
         //* Eavesdropped key
-        let eaves_x: BigUint = BigUint::from(7u32);
-        let eaves_s: BigUint = zkp.solve(&k, &c, &eaves_x);
-        let eaves_res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &eaves_s); //* This will be computed as false.
+        let eaves_x = SecretScalar::new(BigUint::from(7u32));
+        let eaves_s = zkp.solve(&k, &c, &eaves_x);
+        let eaves_res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &eaves_s.expose()).is_ok(); //* This will be computed as false.
 
         //* Assertion
         assert!(!eaves_res);
@@ -183,31 +441,23 @@ mod test {
         let alpha: BigUint = BigUint::from(4u32);
         let beta: BigUint = BigUint::from(9u32);
 
-        let p: BigUint = BigUint::from(23u32); //* Modulus
-        let q: BigUint = BigUint::from(11u32);
-
-        //* ZKP Protocol Structure
-        let zkp:ZKP = ZKP{
-            p: p.clone(),
-            q: q.clone(),
-            alpha: alpha.clone(),
-            beta: beta.clone()
-        };
+        //* ZKP Protocol Structure; p = 23 (Modulus), q = 11
+        let zkp:ZKP = tiny();
 
         //* Secrets
-        let x: BigUint = BigUint::from(6u32);
-        let k: BigUint = BigUint::from(7u32);
+        let x = SecretScalar::new(BigUint::from(6u32));
+        let k = SecretScalar::new(BigUint::from(7u32));
 
         //* Challenge
         let c: BigUint = BigUint::from(4u32);
 
         //* Prover
-        let y1: BigUint = ZKP::pow(&alpha, &x, &p); //* This will be computed as 2
-        let y2: BigUint = ZKP::pow(&beta, &x, &p); //* This will be computed as 3
+        let y1: BigUint = zkp.pow(&alpha, &x.expose()); //* This will be computed as 2
+        let y2: BigUint = zkp.pow(&beta, &x.expose()); //* This will be computed as 3
 
         //* Verifier
-        let r1: BigUint = ZKP::pow(&alpha, &k, &p); //* This will be computed as 8
-        let r2: BigUint = ZKP::pow(&beta, &k, &p); //* This will be computed as 4
+        let r1: BigUint = zkp.pow(&alpha, &k.expose()); //* This will be computed as 8
+        let r2: BigUint = zkp.pow(&beta, &k.expose()); //* This will be computed as 4
 
         //* Assertions
         assert_eq!(y1, BigUint::from(2u32));
@@ -217,21 +467,21 @@ mod test {
         assert_eq!(r2, BigUint::from(4u32));
 
         //* Solve
-        let s: BigUint = zkp.solve(&k, &c, &x); //* This will be computed as 5
+        let s = zkp.solve(&k, &c, &x); //* This will be computed as 5
 
         //* Assertion
-        assert_eq!(s, BigUint::from(5u32));
+        assert_eq!(s.expose(), BigUint::from(5u32));
 
         //* Verify
-        let res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &s); //* This will be computed as true.
+        let res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &s.expose()).is_ok(); //* This will be computed as true.
 
         //* Assertion
         assert!(res); //* Assert to be true
 
         //* Eavesdropped key
-        let eaves_x: BigUint = BigUint::from(7u32);
-        let eaves_s: BigUint = zkp.solve(&k, &c, &eaves_x);
-        let eaves_res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &eaves_s); //* This will be computed as false.
+        let eaves_x = SecretScalar::new(BigUint::from(7u32));
+        let eaves_s = zkp.solve(&k, &c, &eaves_x);
+        let eaves_res: bool = zkp.verify(&r1, &r2, &y1, &y2, &c, &eaves_s.expose()).is_ok(); //* This will be computed as false.
 
         //* Assertion
         assert!(!eaves_res);
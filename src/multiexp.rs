@@ -0,0 +1,169 @@
+// Simultaneous multi-exponentiation (Shamir's trick, generalised by Straus).
+// prod a_i^x_i with one shared chain of squarings instead of one chain per base:
+// - every base gets a small table a_i^0 .. a_i^15 (4-bit windows)
+// - from the top window down: 4 squarings, then one multiplication per base with a non-zero digit
+
+// a^s * b^c with 256-bit s, c: 256 squarings and <= 128 multiplications, against 512 and 128 one by one.
+// Variable time; public exponents only (verify, verify_batch). Secret exponents go through ct.rs.
+
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use num_bigint::BigUint;
+use crate::ct::{by_width, fit, from_uint, to_uint};
+use crate::group::Group;
+use crate::zkp_cp::ZKP;
+
+const WINDOW: usize = 4;
+
+//* output = prod base_i^exp_i mod p; 1 for no terms.
+pub fn multi_pow(terms: &[(&BigUint, &BigUint)], p: &BigUint) -> BigUint {
+    //* Montgomery form needs an odd modulus.
+    let product = || terms.iter().fold(BigUint::from(1u32) % p, |acc, (base, exp)| acc * base.modpow(exp, p) % p);
+    if !p.bit(0) {
+        return product();
+    }
+    by_width!(p.bits(), straus(terms, p)).unwrap_or_else(product)
+}
+
+fn straus<const LIMBS: usize>(terms: &[(&BigUint, &BigUint)], p: &BigUint) -> BigUint {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(p));
+    let one = DynResidue::one(params);
+
+    let tables: Vec<Vec<DynResidue<LIMBS>>> = terms
+        .iter()
+        .map(|(base, _)| {
+            let base = DynResidue::new(&to_uint::<LIMBS>(&fit(base, p)), params);
+            let mut table = vec![one; 1 << WINDOW];
+            for digit in 1..table.len() {
+                table[digit] = table[digit - 1] * base;
+            }
+            table
+        })
+        .collect();
+    let exps: Vec<Vec<u64>> = terms.iter().map(|(_, exp)| exp.to_u64_digits()).collect();
+    let windows = terms.iter().map(|(_, exp)| exp.bits() as usize).max().unwrap_or(0).div_ceil(WINDOW);
+
+    let mut acc = one;
+    for window in (0..windows).rev() {
+        if window + 1 < windows {
+            for _ in 0..WINDOW {
+                acc = acc.square();
+            }
+        }
+        let bit = window * WINDOW;
+        for (table, exp) in tables.iter().zip(&exps) {
+            let digit = exp.get(bit / 64).map_or(0, |word| (word >> (bit % 64)) & ((1 << WINDOW) - 1)) as usize;
+            if digit != 0 {
+                acc *= table[digit];
+            }
+        }
+    }
+    from_uint(&acc.retrieve())
+}
+
+impl<G: Group> ZKP<G> {
+    //* multi_pow
+    // output = prod n_i^exp_i; alpha and beta from their tables if built (fixed_base.rs), the others in one pass.
+    pub fn multi_pow(&self, terms: &[(&G::Element, &BigUint)]) -> G::Element {
+        let (fixed, rest): (Vec<_>, Vec<_>) = terms.iter().copied().partition(|(n, _)| self.table(n).is_some());
+        fixed.iter().fold(self.group.multi_pow(&rest), |acc, (n, exp)| self.group.mul(&acc, &self.pow(n, exp)))
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+    use crate::zkp_cp::SecretScalar;
+
+    fn naive(terms: &[(BigUint, BigUint)], p: &BigUint) -> BigUint {
+        terms.iter().fold(BigUint::from(1u32), |acc, (base, exp)| acc * base.modpow(exp, p) % p)
+    }
+
+    fn refs(terms: &[(BigUint, BigUint)]) -> Vec<(&BigUint, &BigUint)> {
+        terms.iter().map(|(base, exp)| (base, exp)).collect()
+    }
+
+    //* Old verify: alpha^s and y1^c one by one.
+    fn verify_naive<G: Group>(zkp: &ZKP<G>, r1: &G::Element, r2: &G::Element, y1: &G::Element, y2: &G::Element, c: &BigUint, s: &BigUint) -> bool {
+        let g = &zkp.group;
+        *r1 == g.mul(&g.pow(&zkp.alpha, s), &g.pow(y1, c)) && *r2 == g.mul(&g.pow(&zkp.beta, s), &g.pow(y2, c))
+    }
+
+    #[test]
+    fn test_multi_pow_tiny() {
+        let p = BigUint::from(23u32);
+        for a in 0..23u32 {
+            for s in 0..40u32 {
+                let terms = [(BigUint::from(a), BigUint::from(s)), (BigUint::from(9u32), BigUint::from(s * 7))];
+                assert_eq!(multi_pow(&refs(&terms), &p), naive(&terms, &p));
+            }
+        }
+        assert_eq!(multi_pow(&[], &p), BigUint::from(1u32));
+        //* Even modulus: plain product
+        let terms = [(BigUint::from(5u32), BigUint::from(3u32))];
+        assert_eq!(multi_pow(&refs(&terms), &BigUint::from(24u32)), BigUint::from(5u32));
+    }
+
+    //* Proof for a random secret; tampered s when `honest` is false.
+    fn transcript<G: Group>(zkp: &ZKP<G>, seed: u64, honest: bool) -> (G::Element, G::Element, G::Element, G::Element, BigUint, BigUint) {
+        let x = SecretScalar::new(BigUint::from(seed) * 0x9E3779B97F4A7C15u64 % zkp.group.order());
+        let k = zkp.random_secret();
+        let ((y1, y2), (r1, r2)) = (zkp.commit(&x), zkp.commit(&k));
+        let c = zkp.random_scalar();
+        let s = zkp.solve(&k, &c, &x).expose();
+        let s = if honest { s } else { (s + seed % 5 + 1u32) % zkp.group.order() };
+        (y1, y2, r1, r2, c, s)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(48))]
+
+        //* Bases up to 160 bytes (wider than p), exponents up to 40 bytes, up to 6 terms
+        #[test]
+        fn prop_multi_pow_modp(terms in vec((vec(any::<u8>(), 0..160), vec(any::<u8>(), 0..40)), 0..6)) {
+            let p = ZKP::modp().group.p;
+            let terms: Vec<(BigUint, BigUint)> = terms.iter().map(|(b, e)| (BigUint::from_bytes_be(b), BigUint::from_bytes_be(e))).collect();
+            prop_assert_eq!(multi_pow(&refs(&terms), &p), naive(&terms, &p));
+        }
+
+        #[test]
+        fn prop_multi_pow_ristretto255(exps in vec(vec(any::<u8>(), 0..40), 0..6)) {
+            let zkp = ZKP::ristretto255();
+            let points: Vec<_> = (0..exps.len()).map(|_| zkp.commit(&zkp.random_secret()).0).collect();
+            let exps: Vec<BigUint> = exps.iter().map(|e| BigUint::from_bytes_be(e)).collect();
+            let terms: Vec<_> = points.iter().zip(&exps).collect();
+
+            let naive = terms.iter().fold(zkp.group.pow(&zkp.alpha, &BigUint::from(0u32)), |acc, (n, exp)| zkp.group.mul(&acc, &zkp.group.pow(n, exp)));
+            prop_assert_eq!(zkp.multi_pow(&terms), naive);
+        }
+    }
+
+    proptest! {
+        //* Table build per case; fewer cases
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        //* verify agrees with the naive path, with and without tables, on honest and tampered proofs.
+        #[test]
+        fn prop_verify_modp(seed in any::<u64>(), honest in any::<bool>()) {
+            for zkp in [ZKP::modp(), ZKP::modp().with_tables()] {
+                let (y1, y2, r1, r2, c, s) = transcript(&zkp, seed, honest);
+                prop_assert_eq!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s).is_ok(), honest);
+                prop_assert_eq!(verify_naive(&zkp, &r1, &r2, &y1, &y2, &c, &s), honest);
+                prop_assert_eq!(zkp.multi_pow(&[(&zkp.alpha, &s), (&y1, &c)]), zkp.group.mul(&zkp.group.pow(&zkp.alpha, &s), &zkp.group.pow(&y1, &c)));
+            }
+        }
+
+        #[test]
+        fn prop_verify_ristretto255(seed in any::<u64>(), honest in any::<bool>()) {
+            for zkp in [ZKP::ristretto255(), ZKP::ristretto255().with_tables()] {
+                let (y1, y2, r1, r2, c, s) = transcript(&zkp, seed, honest);
+                prop_assert_eq!(zkp.verify(&r1, &r2, &y1, &y2, &c, &s).is_ok(), honest);
+                prop_assert_eq!(verify_naive(&zkp, &r1, &r2, &y1, &y2, &c, &s), honest);
+            }
+        }
+    }
+}
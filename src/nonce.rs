@@ -0,0 +1,229 @@
+// Hedged deterministic nonces (RFC 6979 section 3.2, additional data as in section 3.6).
+// k = HMAC_DRBG(x, domain || group || context || entropy || process || counter); first candidate in [1, q)
+
+// - x is the key: nobody without x can predict k, even with a broken RNG.
+// - context, process and counter: two calls never feed the DRBG the same input, so k does not repeat
+//   (a repeated k for two challenges reveals x = (s1 - s2) / (c2 - c1)).
+//   counter: per process, from 0. process: pid and start time (ns), so that a restart or a second client
+//   with the same x and context (login_transcript in the same second) does not replay the counter.
+// - entropy: fresh randomness on top; with a working RNG k is unique whatever the rest.
+
+// Without entropy, uniqueness across processes rests on the process tag: two processes collide only with
+// the same pid and the same start time, to the nanosecond (a clock that is stuck or very coarse).
+// - HMAC: SHA-512.
+
+use std::sync::{atomic::{AtomicU64, Ordering}, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+use crate::ct;
+use crate::group::Group;
+use crate::zkp_cp::{SecretScalar, ZKP};
+
+type HmacSha512 = Hmac<Sha512>;
+
+pub const NONCE_DOMAIN: &[u8] = b"zkp_chaum_pedersen/nonce/v1";
+
+const ENTROPY_LEN: usize = 32;
+const HASH_LEN: usize = 64;
+
+//* Nonces drawn by this process.
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+//* pid || start time in ns since 1970; taken once, by the first nonce of the process.
+static PROCESS: OnceLock<[u8; 20]> = OnceLock::new();
+
+fn process_tag() -> &'static [u8] {
+    PROCESS.get_or_init(now_tag)
+}
+
+fn now_tag() -> [u8; 20] {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let mut tag = [0u8; 20];
+    tag[..4].copy_from_slice(&std::process::id().to_be_bytes());
+    tag[4..].copy_from_slice(&nanos.to_be_bytes());
+    tag
+}
+
+fn hmac(key: &[u8], parts: &[&[u8]]) -> Zeroizing<Vec<u8>> {
+    let mut mac = HmacSha512::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    Zeroizing::new(mac.finalize().into_bytes().to_vec())
+}
+
+//* HMAC_DRBG state (K, V); every byte of it is derived from x.
+struct Drbg {
+    key: Zeroizing<Vec<u8>>,
+    v: Zeroizing<Vec<u8>>,
+}
+
+impl Drbg {
+    //* Steps b - g
+    fn new(seed: &[&[u8]]) -> Self {
+        let mut drbg = Drbg { key: Zeroizing::new(vec![0u8; HASH_LEN]), v: Zeroizing::new(vec![1u8; HASH_LEN]) };
+        drbg.update(seed, 0x00);
+        drbg.update(seed, 0x01);
+        drbg
+    }
+
+    //* K = HMAC_K(V || marker || seed), V = HMAC_K(V)
+    fn update(&mut self, seed: &[&[u8]], marker: u8) {
+        let marker = [marker];
+        let mut parts: Vec<&[u8]> = vec![&self.v, &marker];
+        parts.extend_from_slice(seed);
+        self.key = hmac(&self.key, &parts);
+        self.v = hmac(&self.key, &[&self.v]);
+    }
+
+    //* Step h.2: V = HMAC_K(V) until len bytes
+    fn generate(&mut self, len: usize) -> Zeroizing<Vec<u8>> {
+        let mut t = Zeroizing::new(Vec::with_capacity(len + HASH_LEN));
+        while t.len() < len {
+            self.v = hmac(&self.key, &[&self.v]);
+            t.extend_from_slice(&self.v);
+        }
+        t.truncate(len);
+        t
+    }
+}
+
+impl<G: Group> ZKP<G> {
+    //* nonce
+    // output = k for a proof about x; context binds it to what is being proven (ProtocolTranscript::fingerprint).
+    pub fn nonce(&self, x: &SecretScalar, context: &[u8]) -> SecretScalar {
+        let mut entropy = Zeroizing::new([0u8; ENTROPY_LEN]);
+        rand::thread_rng().fill_bytes(entropy.as_mut());
+
+        self.derive_nonce(x, context, entropy.as_ref(), process_tag(), COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn derive_nonce(&self, x: &SecretScalar, context: &[u8], entropy: &[u8], process: &[u8], counter: u64) -> SecretScalar {
+        let q = self.group.order();
+        let q_bits = q.bits() as usize;
+        let len = q_bits.div_ceil(8);
+
+        //* int2octets(x); additional data k' length-prefixed field by field
+        let x_bytes = x.to_bytes_be(self.group.scalar_len());
+        let mut data = Zeroizing::new(Vec::new());
+        let (params, counter) = (self.group.encode_params(), counter.to_be_bytes());
+        let fields = [NONCE_DOMAIN].into_iter()
+            .chain(params.iter().map(Vec::as_slice))
+            .chain([context, entropy, process, &counter]);
+        for field in fields {
+            data.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            data.extend_from_slice(field);
+        }
+
+        let mut drbg = Drbg::new(&[&x_bytes, &data]);
+        loop {
+            //* bits2int: leftmost q_bits bits
+            let t = drbg.generate(len);
+            if let Some(k) = ct::bits2int(&t, q) {
+                return k;
+            }
+            //* Step h.3
+            drbg.update(&[], 0x00);
+        }
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+    use num_bigint::BigUint;
+    use crate::zkp_cp::ModPGroup;
+
+    const PROC: &[u8] = &[1u8; 20];
+
+    #[test]
+    fn test_nonce_deterministic() {
+        let zkp = ZKP::modp();
+        let x = zkp.random_secret();
+        let context = zkp.login_transcript("alice", "auth.example", 1700000000).fingerprint();
+        let entropy = [7u8; ENTROPY_LEN];
+
+        let k = zkp.derive_nonce(&x, &context, &entropy, PROC, 0);
+        assert_eq!(k.expose(), zkp.derive_nonce(&x, &context, &entropy, PROC, 0).expose());
+        assert!(k.expose() != BigUint::from(0u32) && k.expose() < *zkp.group.order());
+
+        //* Every input changes k
+        let other_x = SecretScalar::new(x.expose() + 1u32);
+        assert_ne!(k.expose(), zkp.derive_nonce(&other_x, &context, &entropy, PROC, 0).expose());
+        assert_ne!(k.expose(), zkp.derive_nonce(&x, &zkp.login_transcript("alice", "auth.example", 1700000001).fingerprint(), &entropy, PROC, 0).expose());
+        assert_ne!(k.expose(), zkp.derive_nonce(&x, &context, &[8u8; ENTROPY_LEN], PROC, 0).expose());
+        assert_ne!(k.expose(), zkp.derive_nonce(&x, &context, &entropy, PROC, 1).expose());
+        assert_ne!(k.expose(), zkp.derive_nonce(&x, &context, &entropy, &[2u8; 20], 0).expose());
+        assert_ne!(k.expose(), ZKP::ristretto255().derive_nonce(&x, &context, &entropy, PROC, 0).expose());
+    }
+
+    #[test]
+    fn test_nonce_range() {
+        //* q = 11: most candidates are rejected (4 bits, 16 values)
+        let zkp = ZKP {
+            group: ModPGroup { p: BigUint::from(23u32), q: BigUint::from(11u32), g: BigUint::from(4u32), name: None },
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+            tables: None,
+        };
+        let x = SecretScalar::new(BigUint::from(6u32));
+        let nonces: HashSet<BigUint> = (0..200u64)
+            .map(|counter| zkp.derive_nonce(&x, b"ctx", &[0u8; ENTROPY_LEN], PROC, counter).expose())
+            .collect();
+        assert_eq!(nonces, (1..11u32).map(BigUint::from).collect());
+    }
+
+    #[test]
+    fn test_nonce_broken_rng() {
+        //* The RNG returns the same bytes every time; the counter keeps nonces apart.
+        let zkp = ZKP::modp();
+        let x = zkp.random_secret();
+        let nonces: HashSet<BigUint> = (0..100)
+            .map(|_| zkp.derive_nonce(&x, b"ctx", &[0u8; ENTROPY_LEN], process_tag(), COUNTER.fetch_add(1, Ordering::Relaxed)).expose())
+            .collect();
+        assert_eq!(nonces.len(), 100);
+
+        //* A restarted or second process: same x, context, entropy and counter; another start time.
+        process_tag();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        let restarted = now_tag();
+        assert_ne!(process_tag(), restarted);
+        let k = |process: &[u8]| zkp.derive_nonce(&x, b"ctx", &[0u8; ENTROPY_LEN], process, 0).expose();
+        assert_ne!(k(process_tag()), k(&restarted));
+    }
+
+    //* Same secret, same context: k = s + c * x mod q is recovered from each proof; none repeats.
+    fn check_proofs_never_share_k<G: Group>(zkp: &ZKP<G>) {
+        let x = zkp.random_secret();
+        let (y1, y2) = zkp.commit(&x);
+        let transcript = zkp.login_transcript("alice", "auth.example", 1700000000);
+        let q = zkp.group.order();
+
+        let nonces: HashSet<BigUint> = (0..100)
+            .map(|_| {
+                let proof = zkp.prove_noninteractive(&x, &transcript);
+                let c = zkp.challenge(&transcript, &y1, &y2, &proof.r1, &proof.r2);
+                (&proof.s + c * x.expose()) % q
+            })
+            .collect();
+        assert_eq!(nonces.len(), 100);
+    }
+
+    #[test]
+    fn test_proofs_never_share_k_modp() {
+        check_proofs_never_share_k(&ZKP::modp());
+    }
+
+    #[test]
+    fn test_proofs_never_share_k_ristretto255() {
+        check_proofs_never_share_k(&ZKP::ristretto255());
+    }
+}
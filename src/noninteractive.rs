@@ -0,0 +1,182 @@
+// Non-interactive Chaum-Pedersen Protocol (Fiat-Shamir transform).
+// The verifier's random challenge is replaced by one squeezed from the transcript (transcript.rs)
+// of everything it would have seen.
+
+// transcript = flow, group, username, server identity, flow data; then y1, y2, r1, r2
+// - login: timestamp; the server rejects stale ones (replay)
+// - register: nothing more; ties y1, y2 to the username that registers them
+// - rotate: timestamp and the new y1, y2, salt, KDF costs, proof for the new key; the proof only installs these values
+// - rotate-key: timestamp; proof of possession of the new key (y1, y2 absorbed as the statement), like register
+
+use num_bigint::BigUint;
+use crate::group::Group;
+use crate::kdf::KdfParams;
+use crate::transcript::{Flow, ProtocolTranscript};
+use crate::zkp_cp::{SecretScalar, ZkpError, ZKP};
+
+//* Proof of log_alpha(y1) = log_beta(y2); c is recomputed by the verifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proof<G: Group> {
+    pub r1: G::Element,
+    pub r2: G::Element,
+    pub s: BigUint,
+}
+
+impl<G: Group> ZKP<G> {
+    //* One-shot login at this time.
+    pub fn login_transcript(&self, username: &str, server_id: &str, timestamp: u64) -> ProtocolTranscript {
+        let mut transcript = self.transcript(Flow::Login, username, server_id);
+        transcript.append_u64(b"timestamp", timestamp);
+        transcript
+    }
+
+    //* Proof of possession at registration.
+    pub fn registration_transcript(&self, username: &str, server_id: &str) -> ProtocolTranscript {
+        self.transcript(Flow::Register, username, server_id)
+    }
+
+    //* Credential rotation; binds the proof to the new credentials it installs and to their proof of possession (if any).
+    #[allow(clippy::too_many_arguments)]
+    pub fn rotation_transcript(&self, username: &str, server_id: &str, timestamp: u64, y1: &[u8], y2: &[u8], salt: &[u8], kdf: &KdfParams, new_proof: Option<&Proof<G>>) -> ProtocolTranscript {
+        let mut transcript = self.transcript(Flow::Rotate, username, server_id);
+        transcript.append_u64(b"timestamp", timestamp);
+        transcript.append_message(b"new-y1", y1);
+        transcript.append_message(b"new-y2", y2);
+        transcript.append_message(b"new-salt", salt);
+        transcript.append_message(b"new-kdf", &kdf.to_bytes());
+        match new_proof {
+            Some(proof) => {
+                transcript.append_message(b"new-proof-r1", &self.group.encode(&proof.r1));
+                transcript.append_message(b"new-proof-r2", &self.group.encode(&proof.r2));
+                transcript.append_message(b"new-proof-s", &self.group.encode_scalar(&proof.s));
+            }
+            None => transcript.append_message(b"new-proof", b""),
+        }
+        transcript
+    }
+
+    //* Proof of possession of the new key at rotation; made with the new x.
+    pub fn rotation_key_transcript(&self, username: &str, server_id: &str, timestamp: u64) -> ProtocolTranscript {
+        let mut transcript = self.transcript(Flow::RotateKey, username, server_id);
+        transcript.append_u64(b"timestamp", timestamp);
+        transcript
+    }
+
+    //* Prover: commit, squeeze, solve; no interaction with the verifier.
+    pub fn prove_noninteractive(&self, x: &SecretScalar, transcript: &ProtocolTranscript) -> Proof<G> {
+        let (y1, y2) = self.commit(x);
+
+        let k = self.nonce(x, &transcript.fingerprint());
+        let (r1, r2) = self.commit(&k);
+
+        let c = self.challenge(transcript, &y1, &y2, &r1, &r2);
+        let s = self.solve(&k, &c, x);
+
+        //* s is public once sent; the proof carries a copy.
+        Proof { r1, r2, s: s.expose() }
+    }
+
+    //* Verifier: recompute c from the transcript and run the usual verification.
+    pub fn verify_noninteractive(
+        &self,
+        y1: &G::Element,
+        y2: &G::Element,
+        proof: &Proof<G>,
+        transcript: &ProtocolTranscript,
+    ) -> Result<(), ZkpError> {
+        let c = self.challenge(transcript, y1, y2, &proof.r1, &proof.r2);
+
+        self.verify(&proof.r1, &proof.r2, y1, y2, &c, &proof.s)
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SERVER: &str = "auth.example";
+
+    fn check_noninteractive<G: Group>(zkp: &ZKP<G>) {
+        let x = zkp.random_secret();
+        let (y1, y2) = zkp.commit(&x);
+        let transcript = zkp.login_transcript("alice", SERVER, 1700000000);
+
+        let proof = zkp.prove_noninteractive(&x, &transcript);
+        assert!(zkp.verify_noninteractive(&y1, &y2, &proof, &transcript).is_ok());
+
+        //* Bound to the timestamp, the user, the server and the flow
+        for other in [
+            zkp.login_transcript("alice", SERVER, 1700000001),
+            zkp.login_transcript("bob", SERVER, 1700000000),
+            zkp.login_transcript("alice", "evil.example", 1700000000),
+            zkp.registration_transcript("alice", SERVER),
+        ] {
+            assert!(zkp.verify_noninteractive(&y1, &y2, &proof, &other).is_err());
+        }
+
+        //* Bound to the statement
+        let (other_y1, other_y2) = zkp.commit(&SecretScalar::new(x.expose() + 1u32));
+        assert!(zkp.verify_noninteractive(&other_y1, &other_y2, &proof, &transcript).is_err());
+
+        //* Tampered response
+        let tampered = Proof { s: (&proof.s + 1u32) % zkp.group.order(), ..proof.clone() };
+        assert!(zkp.verify_noninteractive(&y1, &y2, &tampered, &transcript).is_err());
+
+        //* Wrong secret
+        let forged = zkp.prove_noninteractive(&SecretScalar::new(x.expose() + 1u32), &transcript);
+        assert!(zkp.verify_noninteractive(&y1, &y2, &forged, &transcript).is_err());
+    }
+
+    #[test]
+    fn test_noninteractive_modp() {
+        check_noninteractive(&ZKP::modp());
+    }
+
+    #[test]
+    fn test_noninteractive_ristretto255() {
+        check_noninteractive(&ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_rotation_transcript() {
+        let zkp = ZKP::modp();
+        let kdf = KdfParams::default();
+        let fingerprint = |y1: &[u8], y2: &[u8], salt: &[u8], kdf: &KdfParams| zkp.rotation_transcript("alice", SERVER, 5, y1, y2, salt, kdf, None).fingerprint();
+        let rotation = fingerprint(b"y1", b"y2", b"salt", &kdf);
+
+        assert_ne!(rotation, zkp.login_transcript("alice", SERVER, 5).fingerprint());
+        //* Every new credential is bound
+        assert_ne!(rotation, fingerprint(b"y1", b"y2", b"pepper", &kdf));
+        assert_ne!(rotation, fingerprint(b"y1", b"y2", b"salt", &KdfParams { iterations: 3, ..kdf }));
+        //* Framing: moving a byte between fields changes the transcript
+        assert_ne!(rotation, fingerprint(b"y1y", b"2", b"salt", &kdf));
+
+        //* The proof for the new key is bound too; it is made on its own flow.
+        let new_x = zkp.random_secret();
+        let new_proof = zkp.prove_noninteractive(&new_x, &zkp.rotation_key_transcript("alice", SERVER, 5));
+        let (y1, y2) = zkp.commit(&new_x);
+        assert!(zkp.verify_noninteractive(&y1, &y2, &new_proof, &zkp.rotation_key_transcript("alice", SERVER, 5)).is_ok());
+        assert!(zkp.verify_noninteractive(&y1, &y2, &new_proof, &zkp.rotation_key_transcript("alice", SERVER, 6)).is_err());
+        assert!(zkp.verify_noninteractive(&y1, &y2, &new_proof, &zkp.registration_transcript("alice", SERVER)).is_err());
+        let with_proof = zkp.rotation_transcript("alice", SERVER, 5, b"y1", b"y2", b"salt", &kdf, Some(&new_proof)).fingerprint();
+        assert_ne!(rotation, with_proof);
+        let other_proof = Proof { s: (&new_proof.s + 1u32) % zkp.group.order(), ..new_proof };
+        assert_ne!(with_proof, zkp.rotation_transcript("alice", SERVER, 5, b"y1", b"y2", b"salt", &kdf, Some(&other_proof)).fingerprint());
+    }
+
+    #[test]
+    fn test_registration_transcript() {
+        let zkp = ZKP::modp();
+        let x = zkp.random_secret();
+        let (y1, y2) = zkp.commit(&x);
+
+        let proof = zkp.prove_noninteractive(&x, &zkp.registration_transcript("alice", SERVER));
+        assert!(zkp.verify_noninteractive(&y1, &y2, &proof, &zkp.registration_transcript("alice", SERVER)).is_ok());
+        //* Someone else's keys can not be registered under another name with the same proof.
+        assert!(zkp.verify_noninteractive(&y1, &y2, &proof, &zkp.registration_transcript("mallory", SERVER)).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+// Parameter generation tool.
+// - paramgen generate <p_bits> <q_bits>: fresh (p, q, alpha, beta) with primality certificates and the seed
+//   (q_bits = p_bits - 1 for a safe prime)
+// - paramgen validate <file | ->: p, q prime, q | p - 1, alpha, beta of order q (and derived from the seed)
+
+// The output of generate is the input of validate, and of the server (ZKP_GROUP=file:<path>).
+
+use std::{io::Read, process::exit};
+use zkp_chaum_pedersen::zkp_cp::{generate, GroupParams};
+
+fn usage() -> ! {
+    eprintln!("usage: paramgen generate <p_bits> <q_bits>");
+    eprintln!("       paramgen validate <file | ->");
+    exit(2)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args[..] {
+        ["generate", p_bits, q_bits] => {
+            let (Ok(p_bits), Ok(q_bits)) = (p_bits.parse(), q_bits.parse()) else { usage() };
+            match generate(p_bits, q_bits) {
+                Ok(params) => print!("{}", params),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    exit(1)
+                }
+            }
+        }
+        ["validate", path] => {
+            let mut text = String::new();
+            let read = match path {
+                "-" => std::io::stdin().read_to_string(&mut text).map(|_| ()),
+                _ => std::fs::read_to_string(path).map(|t| text = t),
+            };
+            if let Err(e) = read {
+                eprintln!("Cannot read {}: {}", path, e);
+                exit(1)
+            }
+
+            match GroupParams::parse(&text).and_then(|params| params.validate().map(|_| params)) {
+                Ok(params) => println!("OK: {} bit p, {} bit q", params.p.bits(), params.q.bits()),
+                Err(e) => {
+                    println!("INVALID: {}", e);
+                    exit(1)
+                }
+            }
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,441 @@
+// Group parameters: verifiable generators, generation and validation.
+
+// Verifiable derivation of group generators (FIPS 186-4 A.2.3).
+// Nobody knows log_alpha(beta) when beta is hashed into the group from a public seed.
+
+// e = (p - 1) / q
+// for count = 1, 2, ...:
+//   W = SHA-256(seed || "ggen" || index || count)
+//   g = W^e mod p; done if g >= 2
+// index tells apart generators derived from the same seed (8 bits); count is 16 bits.
+
+// Anyone can re-run the derivation from the published seed (verify_generator, FIPS 186-4 A.2.4).
+
+// Generation (generate)
+// - Schnorr group: p = 2 * t * q * f + 1, q of the requested size; f > sqrt(p) certifies p.
+// - Safe prime: p = 2q + 1 (q_bits = p_bits - 1); q > sqrt(p) certifies p.
+// - alpha, beta: derived from a fresh random seed (ALPHA_INDEX, BETA_INDEX).
+// Primality certificates are Pocklington chains: no probabilistic step, anyone can re-check them.
+
+use std::fmt;
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Integer;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use crate::group::ModPGroup;
+use crate::validation::ParamError;
+use crate::zkp_cp::ZKP;
+
+//* index of alpha in generated groups; named groups come with their own generator.
+pub const ALPHA_INDEX: u8 = 0;
+
+//* index of beta; alpha is the group's own generator.
+pub const BETA_INDEX: u8 = 1;
+
+//* Bottom of a certificate chain; proven by trial division.
+const SMALL_PRIME_BITS: u64 = 32;
+
+//* Smallest q accepted by generate().
+pub const MIN_Q_BITS: u64 = 64;
+
+//* Seed of beta for a named group.
+pub fn beta_seed(group_name: &str) -> Vec<u8> {
+    format!("zkp_chaum_pedersen/{}/beta", group_name).into_bytes()
+}
+
+//* None if q does not divide p - 1 or every count is exhausted.
+pub fn derive_generator(p: &BigUint, q: &BigUint, seed: &[u8], index: u8) -> Option<BigUint> {
+    let one = BigUint::from(1u32);
+    if (p - &one) % q != BigUint::from(0u32) {
+        return None;
+    }
+    let e = (p - &one) / q;
+
+    for count in 1..=u16::MAX {
+        let w = Sha256::new()
+            .chain_update(seed)
+            .chain_update(b"ggen")
+            .chain_update([index])
+            .chain_update(count.to_be_bytes())
+            .finalize();
+
+        let g = BigUint::from_bytes_be(&w).modpow(&e, p);
+        if g > one {
+            return Some(g);
+        }
+    }
+    None
+}
+
+//* 1 < g < p, g^q = 1 and g is exactly what seed and index derive.
+pub fn verify_generator(p: &BigUint, q: &BigUint, seed: &[u8], index: u8, g: &BigUint) -> bool {
+    let one = BigUint::from(1u32);
+    if *g <= one || g >= p || g.modpow(q, p) != one {
+        return false;
+    }
+    derive_generator(p, q, seed, index).as_ref() == Some(g)
+}
+
+
+//* Pocklington step: n is prime if f (the previous prime of the chain) divides n - 1, f > sqrt(n),
+//* a^(n - 1) = 1 mod n and gcd(a^((n - 1) / f) - 1, n) = 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PocklingtonStep {
+    pub n: BigUint,
+    pub a: BigUint,
+}
+
+//* Chain of primes, each certified by the one before; the first is small enough for trial division.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimeCertificate {
+    pub steps: Vec<PocklingtonStep>,
+}
+
+impl PrimeCertificate {
+    //* true if the chain proves n prime.
+    pub fn verify(&self, n: &BigUint) -> bool {
+        let (first, last) = match (self.steps.first(), self.steps.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return false,
+        };
+        if first.n.bits() > SMALL_PRIME_BITS || !is_small_prime(&first.n) || last.n != *n {
+            return false;
+        }
+        self.steps.windows(2).all(|pair| pocklington(&pair[1].n, &pair[0].n, &pair[1].a))
+    }
+}
+
+//* n < 2^32 by trial division
+fn is_small_prime(n: &BigUint) -> bool {
+    let n = match u64::try_from(n) {
+        Ok(n) => n,
+        Err(_) => return false,
+    };
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| n % d != 0)
+}
+
+fn pocklington(n: &BigUint, f: &BigUint, a: &BigUint) -> bool {
+    let one = BigUint::from(1u32);
+    if *n <= one || f * f <= *n {
+        return false;
+    }
+    let n_minus_one = n - 1u32;
+    if &n_minus_one % f != BigUint::from(0u32) || a.modpow(&n_minus_one, n) != one {
+        return false;
+    }
+    let z = a.modpow(&(&n_minus_one / f), n);
+    ((z + &n_minus_one) % n).gcd(n) == one
+}
+
+//* Witness a for n, or None if n is composite (or no small witness was found).
+fn find_witness(n: &BigUint, f: &BigUint) -> Option<BigUint> {
+    //* Fermat base 2 first; composites would otherwise cost one exponentiation per candidate witness.
+    let two = BigUint::from(2u32);
+    if two.modpow(&(n - 1u32), n) != BigUint::from(1u32) {
+        return None;
+    }
+    (2u32..64).map(BigUint::from).find(|a| pocklington(n, f, a))
+}
+
+//* Cheap filter before the modular exponentiations.
+fn has_small_factor(n: &BigUint) -> bool {
+    (3u32..2000).step_by(2).any(|d| n % d == BigUint::from(0u32) && *n != BigUint::from(d))
+}
+
+//* Size of the prime certifying a `bits`-bit prime: f^2 >= 2^(bits + 1) > n.
+fn cert_bits(bits: u64) -> u64 {
+    bits.div_ceil(2) + 1
+}
+
+//* Prime n = f * factor * m + 1 of exactly `bits` bits (factor even); f has at least cert_bits(bits) bits.
+//* sieve: extra cheap condition on the candidates.
+fn extend(f: &BigUint, factor: &BigUint, bits: u64, sieve: impl Fn(&BigUint) -> bool) -> (BigUint, BigUint) {
+    let step = f * factor;
+    let lo = ((BigUint::from(1u32) << (bits - 1)) - 1u32).div_ceil(&step);
+    let hi = ((BigUint::from(1u32) << bits) - 2u32) / &step;
+    let mut rng = rand::thread_rng();
+
+    loop {
+        let n = &step * rng.gen_biguint_range(&lo, &(&hi + 1u32)) + 1u32;
+        if has_small_factor(&n) || !sieve(&n) {
+            continue;
+        }
+        if let Some(a) = find_witness(&n, f) {
+            return (n, a);
+        }
+    }
+}
+
+//* Random prime of exactly `bits` bits with its certificate.
+pub fn prove_prime(bits: u64) -> (BigUint, PrimeCertificate) {
+    let mut rng = rand::thread_rng();
+
+    if bits <= SMALL_PRIME_BITS {
+        loop {
+            let n = rng.gen_biguint(bits) | (BigUint::from(1u32) << (bits - 1)) | BigUint::from(1u32);
+            if is_small_prime(&n) {
+                let step = PocklingtonStep { n: n.clone(), a: BigUint::from(0u32) };
+                return (n, PrimeCertificate { steps: vec![step] });
+            }
+        }
+    }
+
+    let (f, mut cert) = prove_prime(cert_bits(bits));
+    let (n, a) = extend(&f, &BigUint::from(2u32), bits, |_| true);
+    cert.steps.push(PocklingtonStep { n: n.clone(), a });
+    (n, cert)
+}
+
+//* Generated (or loaded) group with everything needed to re-check it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupParams {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub alpha: BigUint,
+    pub beta: BigUint,
+    pub seed: Option<Vec<u8>>, //* alpha and beta derived from it (ALPHA_INDEX, BETA_INDEX)
+    pub p_cert: Option<PrimeCertificate>,
+    pub q_cert: Option<PrimeCertificate>,
+}
+
+//* Fresh group; q_bits = p_bits - 1 gives a safe prime.
+pub fn generate(p_bits: u64, q_bits: u64) -> Result<GroupParams, ParamError> {
+    let safe = q_bits + 1 == p_bits;
+    //* Schnorr: 2 * t * q * f with f of cert_bits(p_bits) bits and t >= 1
+    if q_bits < MIN_Q_BITS || (!safe && q_bits + cert_bits(p_bits) + 2 > p_bits) {
+        return Err(ParamError::Invalid("unsupported sizes; need 64 <= q_bits <= p_bits / 2 - 3 or q_bits = p_bits - 1"));
+    }
+
+    let (p, q, p_cert, q_cert) = if safe {
+        generate_safe(p_bits)
+    } else {
+        let (q, q_cert) = prove_prime(q_bits);
+        let (f, mut p_cert) = prove_prime(cert_bits(p_bits));
+        let (p, a) = extend(&f, &(&q * 2u32), p_bits, |_| true);
+        p_cert.steps.push(PocklingtonStep { n: p.clone(), a });
+        (p, q, p_cert, q_cert)
+    };
+
+    let mut seed = vec![0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    let derive = |index| derive_generator(&p, &q, &seed, index).ok_or(ParamError::Invalid("generator derivation exhausted"));
+    let (alpha, beta) = (derive(ALPHA_INDEX)?, derive(BETA_INDEX)?);
+
+    Ok(GroupParams { p, q, alpha, beta, seed: Some(seed), p_cert: Some(p_cert), q_cert: Some(q_cert) })
+}
+
+//* q = 2 * f * m + 1 and p = 2q + 1 both prime; p is certified by q.
+fn generate_safe(p_bits: u64) -> (BigUint, BigUint, PrimeCertificate, PrimeCertificate) {
+    let q_bits = p_bits - 1;
+    let (f, mut q_cert) = prove_prime(cert_bits(q_bits));
+    loop {
+        //* Only candidates whose p survives the sieve too
+        let (q, a) = extend(&f, &BigUint::from(2u32), q_bits, |q| !has_small_factor(&(q * 2u32 + 1u32)));
+        let p: BigUint = &q * 2u32 + 1u32;
+        if let Some(p_witness) = find_witness(&p, &q) {
+            q_cert.steps.push(PocklingtonStep { n: q.clone(), a });
+            let mut p_cert = q_cert.clone();
+            p_cert.steps.push(PocklingtonStep { n: p.clone(), a: p_witness });
+            return (p, q, p_cert, q_cert);
+        }
+    }
+}
+
+impl GroupParams {
+    //* p, q prime (certificates if present, Miller-Rabin otherwise); q | p - 1;
+    //* alpha, beta distinct and of order q; alpha, beta derived from the seed if present.
+    pub fn validate(&self) -> Result<(), ParamError> {
+        if let Some(cert) = &self.p_cert {
+            if !cert.verify(&self.p) {
+                return Err(ParamError::Invalid("p certificate does not verify"));
+            }
+        }
+        if let Some(cert) = &self.q_cert {
+            if !cert.verify(&self.q) {
+                return Err(ParamError::Invalid("q certificate does not verify"));
+            }
+        }
+        self.zkp().check_params()?;
+
+        if let Some(seed) = &self.seed {
+            if !verify_generator(&self.p, &self.q, seed, ALPHA_INDEX, &self.alpha) {
+                return Err(ParamError::Invalid("alpha is not derived from the seed"));
+            }
+            if !verify_generator(&self.p, &self.q, seed, BETA_INDEX, &self.beta) {
+                return Err(ParamError::Invalid("beta is not derived from the seed"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn zkp(&self) -> ZKP {
+        ZKP {
+            group: ModPGroup { p: self.p.clone(), q: self.q.clone(), g: self.alpha.clone(), name: None },
+            alpha: self.alpha.clone(),
+            beta: self.beta.clone(),
+            tables: None,
+        }
+    }
+
+    //* Text form written by paramgen: "key = hex" lines; certificates as "n:a" pairs.
+    pub fn parse(text: &str) -> Result<GroupParams, ParamError> {
+        let mut fields = std::collections::HashMap::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let (key, value) = line.split_once('=').ok_or(ParamError::Invalid("expected key = value"))?;
+            fields.insert(key.trim(), value.trim());
+        }
+
+        let number = |hex: &str| BigUint::parse_bytes(hex.as_bytes(), 16).ok_or(ParamError::Invalid("bad hex number"));
+        let required = |key: &'static str| fields.get(key).copied().ok_or(ParamError::Missing(key)).and_then(number);
+        let cert = |key: &str| -> Result<Option<PrimeCertificate>, ParamError> {
+            let Some(value) = fields.get(key) else { return Ok(None) };
+            let steps = value
+                .split_whitespace()
+                .map(|step| {
+                    let (n, a) = step.split_once(':').ok_or(ParamError::Invalid("expected n:a"))?;
+                    Ok(PocklingtonStep { n: number(n)?, a: number(a)? })
+                })
+                .collect::<Result<Vec<_>, ParamError>>()?;
+            Ok(Some(PrimeCertificate { steps }))
+        };
+        let seed = match fields.get("seed") {
+            Some(seed) => Some(hex::decode(seed).map_err(|_| ParamError::Invalid("bad hex seed"))?),
+            None => None,
+        };
+
+        Ok(GroupParams {
+            p: required("p")?,
+            q: required("q")?,
+            alpha: required("alpha")?,
+            beta: required("beta")?,
+            seed,
+            p_cert: cert("p_cert")?,
+            q_cert: cert("q_cert")?,
+        })
+    }
+}
+
+impl fmt::Display for GroupParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# zkp_chaum_pedersen group: {} bit p, {} bit q", self.p.bits(), self.q.bits())?;
+        for (key, n) in [("p", &self.p), ("q", &self.q), ("alpha", &self.alpha), ("beta", &self.beta)] {
+            writeln!(f, "{} = {:x}", key, n)?;
+        }
+        if let Some(seed) = &self.seed {
+            writeln!(f, "seed = {}", hex::encode(seed))?;
+        }
+        for (key, cert) in [("p_cert", &self.p_cert), ("q_cert", &self.q_cert)] {
+            if let Some(cert) = cert {
+                let steps: Vec<String> = cert.steps.iter().map(|step| format!("{:x}:{:x}", step.n, step.a)).collect();
+                writeln!(f, "{} = {}", key, steps.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_derive_generator_tiny() {
+        let (p, q) = (BigUint::from(23u32), BigUint::from(11u32));
+
+        let g = derive_generator(&p, &q, b"seed", BETA_INDEX).unwrap();
+        //* Order q: g != 1, g^q = 1
+        assert!(g > BigUint::from(1u32));
+        assert_eq!(g.modpow(&q, &p), BigUint::from(1u32));
+
+        //* Deterministic
+        assert_eq!(derive_generator(&p, &q, b"seed", BETA_INDEX), Some(g));
+
+        //* q must divide p - 1
+        assert_eq!(derive_generator(&p, &BigUint::from(7u32), b"seed", BETA_INDEX), None);
+    }
+
+    #[test]
+    fn test_verify_generator() {
+        let (p, q) = (BigUint::from(23u32), BigUint::from(11u32));
+        let g = derive_generator(&p, &q, b"seed", BETA_INDEX).unwrap();
+
+        assert!(verify_generator(&p, &q, b"seed", BETA_INDEX, &g));
+        //* Another element of order q, but not the derived one
+        let other = (1..23u32).map(BigUint::from).find(|h| *h != g && *h > BigUint::from(1u32) && h.modpow(&q, &p) == BigUint::from(1u32)).unwrap();
+        assert!(!verify_generator(&p, &q, b"seed", BETA_INDEX, &other));
+        //* Not of order q
+        assert!(!verify_generator(&p, &q, b"seed", BETA_INDEX, &BigUint::from(1u32)));
+        assert!(!verify_generator(&p, &q, b"seed", BETA_INDEX, &(&g + &p)));
+    }
+
+    #[test]
+    fn test_prime_certificate() {
+        let (n, cert) = prove_prime(256);
+        assert_eq!(n.bits(), 256);
+        assert!(cert.verify(&n));
+        assert!(!cert.verify(&(&n + 2u32)));
+
+        //* Tampered witness
+        let mut forged = cert.clone();
+        forged.steps.last_mut().unwrap().a = BigUint::from(1u32);
+        assert!(!forged.verify(&n));
+
+        //* A composite n with a valid-looking chain below it
+        let mut composite = cert.clone();
+        let f = composite.steps[composite.steps.len() - 2].n.clone();
+        let bad = &f * &f * 2u32 + 1u32;
+        composite.steps.last_mut().unwrap().n = bad.clone();
+        assert!(!composite.verify(&bad));
+    }
+
+    #[test]
+    fn test_generate_schnorr() {
+        let params = generate(512, 160).unwrap();
+        assert_eq!((params.p.bits(), params.q.bits()), (512, 160));
+        assert_eq!(params.validate(), Ok(()));
+
+        //* Text round trip
+        assert_eq!(GroupParams::parse(&params.to_string()), Ok(params.clone()));
+        let without_beta: String = params.to_string().lines().filter(|line| !line.starts_with("beta")).map(|line| line.to_string() + "\n").collect();
+        assert_eq!(GroupParams::parse(&without_beta), Err(ParamError::Missing("beta")));
+        assert_eq!(ParamError::Missing("beta").to_string(), "missing beta");
+
+        //* beta swapped for alpha^x: still of order q, but not derived from the seed
+        let zkp = params.zkp();
+        let trapdoor = GroupParams { beta: zkp.pow(&zkp.alpha, &BigUint::from(12345u32)), ..params.clone() };
+        assert_eq!(trapdoor.validate(), Err(ParamError::Invalid("beta is not derived from the seed")));
+
+        let wrong_q = GroupParams { q: &params.q + 2u32, ..params };
+        assert!(wrong_q.validate().is_err());
+    }
+
+    #[test]
+    fn test_generate_safe_prime() {
+        let params = generate(128, 127).unwrap();
+        assert_eq!(params.p, &params.q * 2u32 + 1u32);
+        assert_eq!(params.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_without_certificates() {
+        //* RFC 5114 group as published: no certificates, no seed
+        let zkp = ZKP::named("rfc5114-2048-256").unwrap();
+        let params = GroupParams {
+            p: zkp.group.p.clone(),
+            q: zkp.group.q.clone(),
+            alpha: zkp.alpha.clone(),
+            beta: zkp.beta.clone(),
+            seed: None,
+            p_cert: None,
+            q_cert: None,
+        };
+        assert_eq!(params.validate(), Ok(()));
+
+        assert!(generate(512, 300).is_err());
+        assert!(generate(512, 32).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+// Secret scalars (password-derived x, nonce k, solution s).
+// Wiped from memory when dropped, never printed; a heap or core dump taken later does not reveal them.
+
+// - Stored as little-endian bytes in a buffer owned here; zeroize() overwrites all of it, spare capacity included (volatile writes)
+// - from_le_bytes(): takes a wiped buffer, no copy is left behind
+// - new(): from a BigUint; num-bigint frees its limbs unwiped, so only for public or test values
+// - le_bytes(): the bytes, for the constant-time arithmetic (ct.rs) and the Ristretto scalar
+// - expose(): a BigUint copy, for encoding and tests; not wiped
+// - Debug: SecretScalar(<redacted>)
+
+// Secrets never go through BigUint in commit(), solve(), nonce() and the KDF; ct.rs builds its limbs from le_bytes().
+
+use std::fmt;
+use num_bigint::BigUint;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+pub struct SecretScalar(Zeroizing<Vec<u8>>);
+
+impl SecretScalar {
+    pub fn new(n: BigUint) -> Self {
+        SecretScalar::from_le_bytes(Zeroizing::new(n.to_bytes_le()))
+    }
+
+    //* High zero bytes are dropped; the buffer keeps its allocation and is wiped as a whole.
+    pub fn from_le_bytes(mut bytes: Zeroizing<Vec<u8>>) -> Self {
+        let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |top| top + 1);
+        bytes.truncate(len);
+        SecretScalar(bytes)
+    }
+
+    pub fn le_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    //* Bit length; reveals the byte length of the secret and its top byte, like BigUint::bits.
+    pub fn bits(&self) -> u64 {
+        match self.0.last() {
+            Some(top) => self.0.len() as u64 * 8 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    //* int2octets: big-endian, left-padded to len bytes, in a wiped buffer.
+    pub fn to_bytes_be(&self, len: usize) -> Zeroizing<Vec<u8>> {
+        let mut out = Zeroizing::new(vec![0u8; len.max(self.0.len())]);
+        let end = out.len();
+        for (i, b) in self.0.iter().enumerate() {
+            out[end - 1 - i] = *b;
+        }
+        out
+    }
+
+    pub fn expose(&self) -> BigUint {
+        BigUint::from_bytes_le(&self.0)
+    }
+}
+
+impl From<BigUint> for SecretScalar {
+    fn from(n: BigUint) -> Self {
+        SecretScalar::new(n)
+    }
+}
+
+//* Zeroizing<Vec<u8>>: every byte up to the capacity, then the length; also done on drop.
+impl Zeroize for SecretScalar {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SecretScalar {}
+
+impl fmt::Debug for SecretScalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretScalar(<redacted>)")
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redacted() {
+        let secret = SecretScalar::new(BigUint::from(0xDEADBEEFu32));
+        let printed = format!("{:?} {:#?}", secret, Some(&secret));
+        assert!(!printed.contains("DEADBEEF") && !printed.contains(&0xDEADBEEFu32.to_string()));
+        assert!(printed.contains("<redacted>"));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let secret = SecretScalar::from_le_bytes(Zeroizing::new(vec![0x01, 0x02, 0x00, 0x00]));
+        assert_eq!(secret.le_bytes(), &[0x01, 0x02]);
+        assert_eq!(secret.bits(), 10);
+        assert_eq!(secret.expose(), BigUint::from(0x0201u32));
+        assert_eq!(*secret.to_bytes_be(4), vec![0x00, 0x00, 0x02, 0x01]);
+
+        let zero = SecretScalar::new(BigUint::from(0u32));
+        assert_eq!((zero.bits(), zero.expose()), (0, BigUint::from(0u32)));
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let value = BigUint::from_bytes_be(&[0xA5; 256]);
+        let mut secret = SecretScalar::new(value.clone());
+        assert_eq!(secret.expose(), value);
+
+        secret.zeroize();
+        assert_eq!(secret.expose(), BigUint::from(0u32));
+
+        assert!(secret.le_bytes().is_empty());
+        assert_eq!(secret.bits(), 0);
+        //* The allocation is kept (wiped in place), so drop does not free a copy elsewhere.
+        assert!(secret.0.capacity() >= 256);
+
+        //* Live buffers: the bytes themselves are overwritten, not only the length.
+        let mut bytes = [0xA5u8; 256];
+        bytes.zeroize();
+        assert!(bytes.iter().all(|b| *b == 0));
+        let mut be = SecretScalar::new(value).to_bytes_be(256);
+        assert!(be.iter().all(|b| *b == 0xA5));
+        be.as_mut_slice().zeroize();
+        assert_eq!(be.len(), 256);
+        assert!(be.iter().all(|b| *b == 0));
+
+        //* Idempotent; drop after zeroize is fine.
+        secret.zeroize();
+    }
+}
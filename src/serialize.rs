@@ -0,0 +1,326 @@
+// Serde support (cargo feature "serde"): parameters, statements, transcripts and proofs
+// for config files, audit logs and test vectors.
+
+// - human-readable formats (JSON): every element and scalar is a lowercase hex string
+// - binary formats (CBOR): the same bytes as a byte string
+// - bytes: the canonical encodings of encoding.rs (Group::encode, encode_scalar, encode_params),
+//   the same bytes the wire carries and the transcripts absorb
+// - one representation per value; anything else (uppercase hex, other lengths, unknown fields) fails,
+//   so serializing what was deserialized gives back the same text or bytes
+
+// ZKP (parameters) implements Serialize / Deserialize itself; deserializing runs check_params().
+// Statements, transcripts and proofs need their group for these bytes: zkp.to_repr() gives their serde form
+// (StatementRepr, TranscriptRepr, ProofRepr), zkp.from_repr() decodes and validates it as the wire does
+// (parse_element, parse_scalar).
+
+use std::fmt;
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use crate::encoding::{EncodingError, Statement, Transcript};
+use crate::group::Group;
+use crate::noninteractive::Proof;
+use crate::validation::{ParamError, ValidationError};
+use crate::zkp_cp::ZKP;
+
+//* Hex string or byte string, depending on the format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Blob(Vec<u8>);
+
+impl Serialize for Blob {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(&self.0))
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+struct BlobVisitor;
+
+impl<'de> de::Visitor<'de> for BlobVisitor {
+    type Value = Blob;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("lowercase hex string or byte string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Blob, E> {
+        match hex::decode(v) {
+            Ok(bytes) if hex::encode(&bytes) == v => Ok(Blob(bytes)),
+            _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Blob, E> {
+        Ok(Blob(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Blob, E> {
+        Ok(Blob(v))
+    }
+}
+
+impl<'de> Deserialize<'de> for Blob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Blob, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BlobVisitor)
+        } else {
+            deserializer.deserialize_bytes(BlobVisitor)
+        }
+    }
+}
+
+//* group: Group::name(); params: the other encode_params() fields.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParamsRepr {
+    group: String,
+    params: Vec<Blob>,
+    alpha: Blob,
+    beta: Blob,
+}
+
+//* Serde form of a Statement; y1, y2 as Group::encode().
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatementRepr {
+    y1: Blob,
+    y2: Blob,
+}
+
+//* Serde form of a Transcript; r1, r2 as Group::encode(), c, s as encode_scalar().
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranscriptRepr {
+    r1: Blob,
+    r2: Blob,
+    c: Blob,
+    s: Blob,
+}
+
+//* Serde form of a Proof; r1, r2 as Group::encode(), s as encode_scalar().
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProofRepr {
+    r1: Blob,
+    r2: Blob,
+    s: Blob,
+}
+
+//* Values with a serde form in the encodings of their group.
+pub trait SerdeRepr<G: Group>: Sized {
+    type Repr: Serialize + for<'de> Deserialize<'de>;
+
+    fn to_repr(&self, zkp: &ZKP<G>) -> Self::Repr;
+
+    //* decode + validate, as decode_statement() / decode_transcript()
+    fn from_repr(repr: Self::Repr, zkp: &ZKP<G>) -> Result<Self, EncodingError>;
+}
+
+impl<G: Group> ZKP<G> {
+    pub fn to_repr<T: SerdeRepr<G>>(&self, value: &T) -> T::Repr {
+        value.to_repr(self)
+    }
+
+    pub fn from_repr<T: SerdeRepr<G>>(&self, repr: T::Repr) -> Result<T, EncodingError> {
+        T::from_repr(repr, self)
+    }
+
+    fn element_blob(&self, e: &G::Element) -> Blob {
+        Blob(self.group.encode(e))
+    }
+
+    fn scalar_blob(&self, n: &num_bigint::BigUint) -> Blob {
+        Blob(self.group.encode_scalar(n))
+    }
+
+    fn parse_element_blob(&self, field: &'static str, blob: Blob) -> Result<G::Element, EncodingError> {
+        self.parse_element(&blob.0).map_err(|e| EncodingError::Invalid(field, e))
+    }
+
+    fn parse_scalar_blob(&self, field: &'static str, blob: Blob) -> Result<num_bigint::BigUint, EncodingError> {
+        self.parse_scalar(&blob.0).map_err(|e| EncodingError::Invalid(field, e))
+    }
+}
+
+//* Parameters only; tables are rebuilt with with_tables().
+impl<G: Group> Serialize for ZKP<G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut fields = self.group.encode_params().into_iter();
+        let group = String::from_utf8(fields.next().unwrap_or_default()).map_err(::serde::ser::Error::custom)?;
+        ParamsRepr { group, params: fields.map(Blob).collect(), alpha: self.element_blob(&self.alpha), beta: self.element_blob(&self.beta) }.serialize(serializer)
+    }
+}
+
+//* Same checks as decode_parameters().
+impl<'de, G: Group> Deserialize<'de> for ZKP<G> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = ParamsRepr::deserialize(deserializer)?;
+        let fields: Vec<Vec<u8>> = [repr.group.into_bytes()].into_iter().chain(repr.params.into_iter().map(|p| p.0)).collect();
+        let group = G::decode_params(&fields).ok_or_else(|| de::Error::custom(EncodingError::Params(ParamError::Invalid("group parameters are not canonical"))))?;
+
+        let element = |field: &'static str, blob: Blob| group.decode(&blob.0).ok_or_else(|| de::Error::custom(EncodingError::Invalid(field, ValidationError::Malformed)));
+        let zkp = ZKP { alpha: element("alpha", repr.alpha)?, beta: element("beta", repr.beta)?, group, tables: None };
+        zkp.check_params().map_err(de::Error::custom)?;
+        Ok(zkp)
+    }
+}
+
+impl<G: Group> SerdeRepr<G> for Statement<G> {
+    type Repr = StatementRepr;
+
+    fn to_repr(&self, zkp: &ZKP<G>) -> StatementRepr {
+        StatementRepr { y1: zkp.element_blob(&self.y1), y2: zkp.element_blob(&self.y2) }
+    }
+
+    fn from_repr(repr: StatementRepr, zkp: &ZKP<G>) -> Result<Self, EncodingError> {
+        Ok(Statement { y1: zkp.parse_element_blob("y1", repr.y1)?, y2: zkp.parse_element_blob("y2", repr.y2)? })
+    }
+}
+
+impl<G: Group> SerdeRepr<G> for Transcript<G> {
+    type Repr = TranscriptRepr;
+
+    fn to_repr(&self, zkp: &ZKP<G>) -> TranscriptRepr {
+        TranscriptRepr { r1: zkp.element_blob(&self.r1), r2: zkp.element_blob(&self.r2), c: zkp.scalar_blob(&self.c), s: zkp.scalar_blob(&self.s) }
+    }
+
+    fn from_repr(repr: TranscriptRepr, zkp: &ZKP<G>) -> Result<Self, EncodingError> {
+        Ok(Transcript {
+            r1: zkp.parse_element_blob("r1", repr.r1)?,
+            r2: zkp.parse_element_blob("r2", repr.r2)?,
+            c: zkp.parse_scalar_blob("c", repr.c)?,
+            s: zkp.parse_scalar_blob("s", repr.s)?,
+        })
+    }
+}
+
+impl<G: Group> SerdeRepr<G> for Proof<G> {
+    type Repr = ProofRepr;
+
+    fn to_repr(&self, zkp: &ZKP<G>) -> ProofRepr {
+        ProofRepr { r1: zkp.element_blob(&self.r1), r2: zkp.element_blob(&self.r2), s: zkp.scalar_blob(&self.s) }
+    }
+
+    fn from_repr(repr: ProofRepr, zkp: &ZKP<G>) -> Result<Self, EncodingError> {
+        Ok(Proof { r1: zkp.parse_element_blob("r1", repr.r1)?, r2: zkp.parse_element_blob("r2", repr.r2)?, s: zkp.parse_scalar_blob("s", repr.s)? })
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serde::de::DeserializeOwned;
+    use crate::zkp_cp::ModPGroup;
+    use crate::zkp_ec::Ristretto255;
+
+    //* JSON and CBOR: value -> text/bytes -> value -> the same text/bytes
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> (T, String) {
+        let json = serde_json::to_string(value).unwrap();
+        let from_json: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&from_json).unwrap(), json);
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(value, &mut cbor).unwrap();
+        let from_cbor: T = ciborium::from_reader(cbor.as_slice()).unwrap();
+        let mut again = Vec::new();
+        ciborium::into_writer(&from_cbor, &mut again).unwrap();
+        assert_eq!(again, cbor);
+
+        (from_json, json)
+    }
+
+    fn check_round_trip<G: Group>(zkp: &ZKP<G>) {
+        let (params, _) = round_trip(zkp);
+        assert!(params.group == zkp.group && params.alpha == zkp.alpha && params.beta == zkp.beta);
+
+        let x = zkp.random_secret();
+        let (y1, y2) = zkp.commit(&x);
+        let statement = Statement::<G> { y1: y1.clone(), y2: y2.clone() };
+        let (repr, json) = round_trip(&zkp.to_repr(&statement));
+        assert_eq!(zkp.from_repr::<Statement<G>>(repr).unwrap(), statement);
+
+        //* The bytes of the wire and of encoding.rs
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["y1"], hex::encode(zkp.group.encode(&y1)));
+
+        let transcript = zkp.login_transcript("alice", "auth.example", 1700000000);
+        let proof = zkp.prove_noninteractive(&x, &transcript);
+        assert_eq!(zkp.from_repr::<Proof<G>>(round_trip(&zkp.to_repr(&proof)).0).unwrap(), proof);
+
+        let c = zkp.challenge(&transcript, &y1, &y2, &proof.r1, &proof.r2);
+        let transcript = Transcript::<G> { r1: proof.r1.clone(), r2: proof.r2.clone(), c, s: proof.s.clone() };
+        let decoded: Transcript<G> = zkp.from_repr(round_trip(&zkp.to_repr(&transcript)).0).unwrap();
+        assert!(zkp.verify(&decoded.r1, &decoded.r2, &y1, &y2, &decoded.c, &decoded.s).is_ok());
+    }
+
+    #[test]
+    fn test_serde_modp() {
+        check_round_trip(&ZKP::modp());
+        check_round_trip(&ZKP::named("rfc5114-2048-256").unwrap());
+    }
+
+    #[test]
+    fn test_serde_ristretto255() {
+        check_round_trip(&ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_serde_json_format() {
+        let zkp = ZKP::modp();
+        let statement: Statement<ModPGroup> = Statement { y1: zkp.alpha.clone(), y2: zkp.beta.clone() };
+        let json = serde_json::to_value(zkp.to_repr(&statement)).unwrap();
+        assert_eq!(json["y1"], hex::encode(zkp.group.encode(&zkp.alpha)));
+        assert_eq!(json["y1"].as_str().unwrap().len(), 2 * zkp.group.element_len());
+
+        //* CBOR: byte strings (128 bytes: 0x58 0x80), not hex
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&zkp.to_repr(&statement), &mut cbor).unwrap();
+        let encoded = [&[0x58, 0x80][..], &zkp.group.encode(&zkp.alpha)].concat();
+        assert!(cbor.windows(encoded.len()).any(|w| w == encoded));
+
+        let json = serde_json::to_value(&zkp).unwrap();
+        assert_eq!(json["group"], "rfc5114-1024-160");
+        assert_eq!(json["params"][0], hex::encode(zkp.group.p.to_bytes_be()));
+    }
+
+    #[test]
+    fn test_serde_rejects_non_canonical() {
+        let zkp = ZKP::modp();
+        let (y1, y2) = (hex::encode(zkp.group.encode(&zkp.alpha)), hex::encode(zkp.group.encode(&zkp.beta)));
+        let statement = |y1: &str, y2: &str| format!(r#"{{"y1":"{}","y2":"{}"}}"#, y1, y2);
+        let reject = |json: &str| match serde_json::from_str::<StatementRepr>(json) {
+            Ok(repr) => zkp.from_repr::<Statement<ModPGroup>>(repr).is_err(),
+            Err(_) => true,
+        };
+        assert!(!reject(&statement(&y1, &y2)));
+        assert!(reject(&statement(&y1.to_uppercase(), &y2)));
+        assert!(reject(&statement(&y1[2..], &y2))); //* shorter
+        assert!(reject(&statement(&format!("00{}", y1), &y2))); //* longer
+        assert!(reject(&statement(&"ff".repeat(128), &y2))); //* >= p
+        assert!(reject(&statement(&hex::encode(zkp.group.encode(&1u32.into())), &y2))); //* identity
+        assert!(reject(&statement("", &y2)));
+        assert!(reject(&format!(r#"{{"y1":"{}","y2":"{}","z":"01"}}"#, y1, y2))); //* unknown field
+        assert!(reject(&format!(r#"{{"y1":"{}"}}"#, y1)));
+
+        //* s >= q: s and s + q verify alike, only s is accepted
+        let x = zkp.random_secret();
+        let proof = zkp.prove_noninteractive(&x, &zkp.login_transcript("alice", "auth.example", 1700000000));
+        let mut json = serde_json::to_value(zkp.to_repr(&proof)).unwrap();
+        json["s"] = hex::encode(zkp.group.encode_scalar(&(&proof.s + &zkp.group.q))).into();
+        assert!(zkp.from_repr::<Proof<ModPGroup>>(serde_json::from_value(json).unwrap()).is_err());
+
+        //* Not a point; parameters that fail check_params
+        let ristretto = ZKP::ristretto255();
+        let repr = serde_json::from_str(&statement(&"ff".repeat(32), &"00".repeat(32))).unwrap();
+        assert!(ristretto.from_repr::<Statement<Ristretto255>>(repr).is_err());
+        let mut params = serde_json::to_value(ZKP::modp()).unwrap();
+        params["beta"] = params["alpha"].clone();
+        assert!(serde_json::from_value::<ZKP>(params).is_err());
+        assert!(serde_json::from_value::<ZKP<Ristretto255>>(serde_json::to_value(ZKP::modp()).unwrap()).is_err());
+    }
+}
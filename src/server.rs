@@ -1,9 +1,13 @@
+use std::{sync::{Arc, Mutex}, collections::{hash_map::Entry, HashMap}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use sha2::{Digest, Sha256};
+use zkp_chaum_pedersen::{error::lock, kdf, session, store, zkp_cp, Backend, PROTOCOL_VERSION};
+use kdf::{KdfParams, SALT_LEN};
+use session::{Session, SessionStore};
+use store::{MemoryStore, UserInfo, UserStore};
+use zkp_cp::{verifier_nonce, Group, Proof, ProtocolTranscript, ZkpError, ZKP};
 use num_bigint::BigUint;
-use std::{sync::Mutex, collections::HashMap};
-use zkp_chaum_pedersen::zkp_cp;
-use zkp_cp::ZKP;
 // tonic lib will be generated in build time; need to use 'pub mod' iOT use macro keywords
-use tonic::{transport::Server, Code, Request, Response, Status};
+use tonic::{transport::Server, Request, Response, Status};
 
 pub mod zkp_auth{ //* Make this module available.
     include!("./zkp_auth.rs");
@@ -11,132 +15,1055 @@ pub mod zkp_auth{ //* Make this module available.
 
 use zkp_auth::auth_service_server::{AuthService, AuthServiceServer};
 //* Factories
-use crate::zkp_auth::{AuthenticationAnswerRequest, AuthenticationAnswerResponse, AuthenticationChallengeRequest, AuthenticationChallengeResponse, RegisterRequest, RegisterResponse};
+use crate::zkp_auth::{AuthenticationAnswerRequest, AuthenticationAnswerResponse, AuthenticationChallengeRequest, AuthenticationChallengeResponse, AuthenticationProofRequest, KdfParameters, KdfParametersRequest, KdfParametersResponse, LogoutResponse, ParametersRequest, ParametersResponse, RegisterRequest, RegisterResponse, RotateCredentialsRequest, RotateCredentialsResponse, SessionRequest, SessionResponse};
+
+//* One-shot proofs are accepted only if their timestamp is this close to the server clock.
+const PROOF_WINDOW_SECS: u64 = 60;
+
+//* Default lifetime of an auth_id; ZKP_CHALLENGE_TTL_SECS overrides it.
+const CHALLENGE_TTL_SECS: u64 = 60;
+
+//* Default lifetime of a session; ZKP_SESSION_TTL_SECS overrides it.
+const SESSION_TTL_SECS: u64 = 3600;
+
+//* Default identity bound into every challenge (the listening address); ZKP_SERVER_ID overrides it.
+const SERVER_ID: &str = "127.0.0.1:50051";
 
 //* Structure for Tonic server
-#[derive(Debug, Default)] //* Use in both purpose; debug and default.
-pub struct AuthImpl{
-    pub user_info_storage: Mutex<HashMap<String,UserInfo>>, //* Insecure due to asynchronous code; need to call mutex to lock this map while updating.
-    //* Hash<String, UserInfo>
-    //* Mutex will block another thread to access while updating.
-    pub auth_id_stroage: Mutex<HashMap<String,String>>,
+pub struct AuthImpl<G: Group>{
+    pub zkp: ZKP<G>, //* Group and generators used by every user of this server.
+    pub server_id: String, //* Bound into every transcript; clients must use the same (ZKP_SERVER_ID).
+    pub beta_seed: Vec<u8>, //* beta = hash_to_group(beta_seed); served so that clients can re-derive beta.
+    pub kdf_floor: KdfParams, //* Weakest KDF costs accepted at registration; raise to harden new users.
+    pub require_registration_proof: bool, //* Reject registrations and rotations without a proof of possession of the new x.
+    pub salt_seed: Vec<u8>, //* Random per run; fake salts of unknown users are derived from it.
+    pub challenge_ttl: Duration, //* An auth_id not answered within this is dropped.
+    pub user_info_storage: Box<dyn UserStore>, //* username -> UserInfo; memory, file or sqlite (ZKP_STORE).
+    pub auth_id_stroage: Mutex<HashMap<String,Challenge>>, //* auth_id -> pending challenge; removed when answered.
+    //* One-shot proofs already accepted (r1 -> timestamp); a proof can not be replayed inside its window.
+    pub used_proof_storage: Mutex<HashMap<Vec<u8>,u64>>,
+    pub session_storage: SessionStore, //* session_id -> owner and expiry
 }
 
-//* Structure for user information
-#[derive(Debug, Default)]
-pub struct UserInfo{
+//* Structure for a login in flight; one per auth_id, so logins of the same user do not clobber each other.
+#[derive(Debug)]
+pub struct Challenge{
     pub username: String,
-
-    //* Registration
-    pub y1: BigUint,
-    pub y2: BigUint,
+    pub expires_at: Instant,
 
     //* authorization
-    pub r1: BigUint,
-    pub r2: BigUint,
+    pub r1: Vec<u8>,
+    pub r2: Vec<u8>,
 
     //* verification
-    pub c: BigUint,
-    pub s: BigUint,
-    pub session_id: String,
+    pub c: Vec<u8>,
+}
+
+//* 0 for a clock set before 1970; such a server rejects every one-shot proof instead of crashing.
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn session_to_msg(session: &Session) -> SessionResponse {
+    SessionResponse{
+        username: session.username.clone(),
+        created_at: unix_secs(session.created_at),
+        expires_at: unix_secs(session.expires_at),
+    }
+}
+
+//* Proto <-> library KDF parameters
+fn kdf_from_msg(msg: &KdfParameters) -> KdfParams {
+    KdfParams{
+        version: msg.version,
+        memory_kib: msg.memory_kib,
+        iterations: msg.iterations,
+        parallelism: msg.parallelism,
+    }
+}
+
+fn kdf_to_msg(kdf: &KdfParams) -> KdfParameters {
+    KdfParameters{
+        version: kdf.version,
+        memory_kib: kdf.memory_kib,
+        iterations: kdf.iterations,
+        parallelism: kdf.parallelism,
+    }
+}
+
+impl<G: Group> AuthImpl<G> {
+    pub fn new(zkp: ZKP<G>, kdf_floor: KdfParams) -> Self {
+        AuthImpl{
+            beta_seed: zkp.group.beta_seed().unwrap_or_default(),
+            zkp,
+            server_id: SERVER_ID.to_string(),
+            kdf_floor,
+            require_registration_proof: false,
+            salt_seed: kdf::gen_salt(),
+            challenge_ttl: Duration::from_secs(CHALLENGE_TTL_SECS),
+            user_info_storage: Box::new(MemoryStore::default()),
+            auth_id_stroage: Mutex::new(HashMap::new()),
+            used_proof_storage: Mutex::new(HashMap::new()),
+            session_storage: SessionStore::new(Duration::from_secs(SESSION_TTL_SECS)),
+        }
+    }
+
+    //* Successful login -> new session.
+    fn open_session(&self, username: &str) -> AuthenticationAnswerResponse {
+        let session = self.session_storage.create(username);
+        AuthenticationAnswerResponse{ session_id: session.session_id, expires_at: unix_secs(session.expires_at) }
+    }
+
+    //* Drop unanswered challenges past their TTL and one-shot proofs past their window.
+    //* Returns the number of evicted challenges.
+    pub fn sweep_expired(&self) -> usize {
+        let now = Instant::now();
+        let auth_id_storage = &mut lock(&self.auth_id_stroage);
+        let before = auth_id_storage.len();
+        auth_id_storage.retain(|_, challenge| challenge.expires_at > now);
+        let evicted = before - auth_id_storage.len();
+
+        let unix_now = unix_secs(SystemTime::now());
+        lock(&self.used_proof_storage).retain(|_, timestamp| unix_now <= *timestamp + PROOF_WINDOW_SECS);
+
+        evicted
+    }
+
+    //* New y1, y2, salt and KDF costs (Register, RotateCredentials); returns the parsed costs.
+    fn check_credentials(&self, y1: &[u8], y2: &[u8], salt: &[u8], kdf: Option<&KdfParameters>) -> Result<KdfParams, ZkpError> {
+        //* Degenerate keys (e.g. y1 = 1) would accept any password.
+        self.zkp.parse_element(y1).map_err(|e| ZkpError::invalid("y1", e))?;
+        self.zkp.parse_element(y2).map_err(|e| ZkpError::invalid("y2", e))?;
+
+        if salt.len() < SALT_LEN {
+            return Err(ZkpError::Rejected(format!("Salt must be at least {} bytes", SALT_LEN)));
+        }
+
+        match kdf.map(kdf_from_msg) {
+            Some(kdf) if kdf.meets(&self.kdf_floor) => Ok(kdf),
+            _ => Err(ZkpError::Rejected("KDF parameters below the server floor".to_string())),
+        }
+    }
+
+    //* One-shot proof: inside the time window and never seen before; the proof is marked as used if it verifies.
+    fn check_proof(&self, user_info: &UserInfo, r1: &[u8], r2: &[u8], s: &[u8], timestamp: u64, transcript: &ProtocolTranscript) -> Result<(), ZkpError> {
+        let now = unix_secs(SystemTime::now());
+
+        //* Stale (or future) proofs are rejected before any group operation.
+        if timestamp.abs_diff(now) > PROOF_WINDOW_SECS {
+            return Err(ZkpError::ProofExpired);
+        }
+
+        let proof = self.parse_proof(r1, r2, s, ["r1", "r2", "s"])?;
+
+        //* Known replays are turned away before the exponentiations.
+        if lock(&self.used_proof_storage).contains_key(r1) {
+            return Err(ZkpError::ProofReplayed);
+        }
+
+        //* Verified without the lock, so that logins do not wait on each other; of two copies in flight,
+        //* only the first to be marked is accepted. A forged proof is never marked and blocks nobody.
+        self.verify_proof(user_info, &proof, transcript)?;
+        match lock(&self.used_proof_storage).entry(r1.to_vec()) {
+            Entry::Occupied(_) => Err(ZkpError::ProofReplayed),
+            Entry::Vacant(entry) => {
+                entry.insert(timestamp);
+                Ok(())
+            }
+        }
+    }
+
+    //* Received one-shot proof; fields names r1, r2, s in errors.
+    fn parse_proof(&self, r1: &[u8], r2: &[u8], s: &[u8], fields: [&'static str; 3]) -> Result<Proof<G>, ZkpError> {
+        Ok(Proof::<G>{
+            r1: self.zkp.parse_element(r1).map_err(|e| ZkpError::invalid(fields[0], e))?,
+            r2: self.zkp.parse_element(r2).map_err(|e| ZkpError::invalid(fields[1], e))?,
+            s: self.zkp.parse_scalar(s).map_err(|e| ZkpError::invalid(fields[2], e))?,
+        })
+    }
+
+    //* Registered user (true), or a decoy for an unknown one (false): fake salt, the KDF floor and keys (beta, alpha).
+    //* The decoy goes through the same checks and exponentiations and then fails as VerificationFailed,
+    //* so a login for an unknown user looks like a wrong password.
+    fn user_info(&self, username: &str) -> Result<(UserInfo, bool), ZkpError> {
+        if let Some(user_info) = self.user_info_storage.get(username)? {
+            return Ok((user_info, true));
+        }
+        let decoy = UserInfo{
+            username: username.to_string(),
+            y1: self.zkp.group.encode(&self.zkp.beta),
+            y2: self.zkp.group.encode(&self.zkp.alpha),
+            salt: self.fake_salt(username),
+            kdf: self.kdf_floor,
+        };
+        Ok((decoy, false))
+    }
+
+    //* Stable per username, unpredictable without the seed.
+    fn fake_salt(&self, username: &str) -> Vec<u8> {
+        let digest = Sha256::new()
+            .chain_update(&self.salt_seed)
+            .chain_update(username.as_bytes())
+            .finalize();
+        digest[..SALT_LEN].to_vec()
+    }
+
+    //* Challenge c from the interactive transcript and a fresh verifier nonce; both encoded for the client.
+    fn gen_challenge(&self, user_info: &UserInfo, r1: &G::Element, r2: &G::Element) -> Result<(Vec<u8>, Vec<u8>), ZkpError> {
+        let group = &self.zkp.group;
+
+        match (group.decode(&user_info.y1), group.decode(&user_info.y2)) {
+            (Some(y1), Some(y2)) => {
+                let nonce = verifier_nonce();
+                let transcript = self.zkp.interactive_transcript(&user_info.username, &self.server_id, &nonce);
+                let c = self.zkp.challenge(&transcript, &y1, &y2, r1, r2);
+                Ok((group.encode_scalar(&c), nonce))
+            }
+            _ => Err(ZkpError::VerificationFailed),
+        }
+    }
+
+    //* Decode the stored transcript and check it against the solution s.
+    //* Stored values were validated when they were received.
+    fn verify(&self, user_info: &UserInfo, challenge: &Challenge, s: &BigUint) -> Result<(), ZkpError> {
+        let group = &self.zkp.group;
+
+        //* Any malformed element fails the verification.
+        let decoded = (
+            group.decode(&challenge.r1),
+            group.decode(&challenge.r2),
+            group.decode(&user_info.y1),
+            group.decode(&user_info.y2),
+        );
+        match (decoded, group.decode_scalar(&challenge.c)) {
+            ((Some(r1), Some(r2), Some(y1), Some(y2)), Some(c)) => self.zkp.verify(&r1, &r2, &y1, &y2, &c, s),
+            _ => Err(ZkpError::VerificationFailed),
+        }
+    }
+
+    //* Check a one-shot proof against the registered y1, y2.
+    fn verify_proof(&self, user_info: &UserInfo, proof: &Proof<G>, transcript: &ProtocolTranscript) -> Result<(), ZkpError> {
+        let group = &self.zkp.group;
+
+        match (group.decode(&user_info.y1), group.decode(&user_info.y2)) {
+            (Some(y1), Some(y2)) => self.zkp.verify_noninteractive(&y1, &y2, proof, transcript),
+            _ => Err(ZkpError::VerificationFailed),
+        }
+    }
 }
 
 #[tonic::async_trait]
-impl AuthService for AuthImpl {
+impl<G: Group> AuthService for AuthImpl<G> {
+    async fn get_parameters(&self, _request: Request<ParametersRequest>) -> Result<Response<ParametersResponse>, Status> {
+        let group = &self.zkp.group;
+
+        Ok(Response::new(ParametersResponse{
+            group: group.name(),
+            protocol_version: PROTOCOL_VERSION,
+            p: group.modulus().to_bytes_be(),
+            q: group.order().to_bytes_be(),
+            alpha: group.encode(&self.zkp.alpha),
+            beta: group.encode(&self.zkp.beta),
+            beta_seed: self.beta_seed.clone(),
+        }))
+    }
+
+    async fn get_kdf_parameters(&self, request: Request<KdfParametersRequest>) -> Result<Response<KdfParametersResponse>, Status> {
+        let req = request.into_inner();
+
+        //* Unknown users look like registered ones; the costs are the ones to register with.
+        let (user_info, _) = self.user_info(&req.username)?;
+
+        Ok(Response::new(KdfParametersResponse{ salt: user_info.salt, kdf: Some(kdf_to_msg(&user_info.kdf)) }))
+    }
+
     async fn register(&self, request: Request<RegisterRequest>) -> Result<Response<RegisterResponse>, Status> {
-        println!("Processing Register: {:?}", request);
         //* Originally, fields in request object are private; need to be converted to access those private fields.
         let req = request.into_inner();
 
         //* Request Processing
         let username = req.username;
+        let kdf = self.check_credentials(&req.y1, &req.y2, &req.salt, req.kdf.as_ref())?;
 
-        let mut user_info:UserInfo = UserInfo::default();
-        user_info.username = username.clone();
-        user_info.y1 = BigUint::from_bytes_be(&req.y1);
-        user_info.y2 = BigUint:: from_bytes_be(&req.y2);
+        let user_info:UserInfo = UserInfo{
+            username: username.clone(),
+            y1: req.y1,
+            y2: req.y2,
+            salt: req.salt,
+            kdf,
+        };
 
-        //* This information is volatile; will be destroyed after this request ends
-            //* need to store temporarily - use hashmap.
-        let mut user_info_storage = &mut self.user_info_storage.lock().unwrap(); //* Block another thread to access.
-        user_info_storage.insert(username, user_info);
+        //* Proof of possession: the client knows x, and y2 uses the same x as y1.
+        match req.proof {
+            Some(proof) => {
+                let proof = self.parse_proof(&proof.r1, &proof.r2, &proof.s, ["proof.r1", "proof.r2", "proof.s"])?;
+                //* Not a login: a bad proof is a bad request.
+                self.verify_proof(&user_info, &proof, &self.zkp.registration_transcript(&username, &self.server_id))
+                    .map_err(|_| ZkpError::Rejected("Invalid proof of possession".to_string()))?;
+            }
+            None if self.require_registration_proof => {
+                return Err(ZkpError::Rejected("Proof of possession required".to_string()).into());
+            }
+            None => {}
+        }
+
+        //* Persisted by the configured backend; survives a restart unless it is the memory store.
+        //* Never overwrites; an existing user changes keys through RotateCredentials.
+        if !self.user_info_storage.insert(user_info).map_err(ZkpError::from)? {
+            return Err(ZkpError::UserExists(username).into());
+        }
 
         //* Ended successfully: return successful response
         Ok(Response::new(RegisterResponse{}))
     }
 
-    async fn create_authentication_challenge(&self, request: Request<AuthenticationChallengeRequest>) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+    async fn rotate_credentials(&self, request: Request<RotateCredentialsRequest>) -> Result<Response<RotateCredentialsResponse>, Status> {
         let req = request.into_inner();
-        let username = req.username;
-
-        //* Storage
-        let mut user_info_storage = &mut self.user_info_storage.lock().unwrap();
-        let mut auth_id_storage = &mut self.auth_id_stroage.lock().unwrap();
+        let kdf = self.check_credentials(&req.y1, &req.y2, &req.salt, req.kdf.as_ref())?;
 
-        //* Some: Option[]; indicated that some value 'exists' <- opposite of None()
-        //* If following value exists, do the following routine.
-        if let Some(user_info) = user_info_storage.get_mut(&username) {
-            user_info.r1 = BigUint::from_bytes_be(&req.r1);
-            user_info.r2 = BigUint::from_bytes_be(&req.r2);
+        let (user_info, registered) = self.user_info(&req.username)?;
 
-            let (_,_,_,q) = ZKP::get_const();
+        let rotated = UserInfo{
+            username: req.username,
+            y1: req.y1,
+            y2: req.y2,
+            salt: req.salt,
+            kdf,
+        };
 
-            let aid = ZKP::gen_rand_str(12);
-            let c = ZKP::gen_rand(&q);
-            user_info.c = c.clone();
+        //* Proof of possession of the new key, checked as at registration; a rejected one does not use up the proof below.
+        let new_proof = match req.new_proof {
+            Some(proof) => {
+                let proof = self.parse_proof(&proof.r1, &proof.r2, &proof.s, ["new_proof.r1", "new_proof.r2", "new_proof.s"])?;
+                self.verify_proof(&rotated, &proof, &self.zkp.rotation_key_transcript(&rotated.username, &self.server_id, req.timestamp))
+                    .map_err(|_| ZkpError::Rejected("Invalid proof of possession".to_string()))?;
+                Some(proof)
+            }
+            None if self.require_registration_proof => {
+                return Err(ZkpError::Rejected("Proof of possession required".to_string()).into());
+            }
+            None => None,
+        };
 
-            auth_id_storage.insert(aid.clone(), username.clone()); //* Store authid - username match, later will used for verification.
-            Ok(Response::new(AuthenticationChallengeResponse{ auth_id: aid, c: c.to_bytes_be()}))
-        } else {
-            //* None of the user exists
-            Err(Status::new(Code::NotFound, format!("User: {} not found", username)))
+        //* Proof of the current secret, bound to the new values and their proof.
+        let transcript = self.zkp.rotation_transcript(&rotated.username, &self.server_id, req.timestamp, &rotated.y1, &rotated.y2, &rotated.salt, &kdf, new_proof.as_ref());
+        self.check_proof(&user_info, &req.r1, &req.r2, &req.s, req.timestamp, &transcript)?;
+        if !registered {
+            return Err(ZkpError::VerificationFailed.into());
         }
 
+        self.user_info_storage.put(rotated).map_err(ZkpError::from)?;
 
+        Ok(Response::new(RotateCredentialsResponse{}))
+    }
+
+    async fn create_authentication_challenge(&self, request: Request<AuthenticationChallengeRequest>) -> Result<Response<AuthenticationChallengeResponse>, Status> {
+        let req = request.into_inner();
+        let username = req.username;
+
+        let r1 = self.zkp.parse_element(&req.r1).map_err(|e| ZkpError::invalid("r1", e))?;
+        let r2 = self.zkp.parse_element(&req.r2).map_err(|e| ZkpError::invalid("r2", e))?;
+
+        //* Unknown users get a challenge too; their answer fails in VerifyAuthentication.
+        let (user_info, _) = self.user_info(&username)?;
+        let aid = ZKP::gen_rand_str(12);
+        let (c, nonce) = self.gen_challenge(&user_info, &r1, &r2)?;
+
+        let challenge = Challenge{
+            username,
+            expires_at: Instant::now() + self.challenge_ttl,
+            r1: req.r1,
+            r2: req.r2,
+            c: c.clone(),
+        };
+
+        let auth_id_storage = &mut lock(&self.auth_id_stroage);
+        auth_id_storage.insert(aid.clone(), challenge); //* Store authid - challenge match, later will used for verification.
+        Ok(Response::new(AuthenticationChallengeResponse{ auth_id: aid, c, nonce }))
     }
 
     async fn verify_authentication(&self, request: Request<AuthenticationAnswerRequest>) -> Result<Response<AuthenticationAnswerResponse>, Status> {
         let req = request.into_inner();
         let aid = req.auth_id;
-        let s = BigUint::from_bytes_be(&req.s);
-        //* Storage
-        let mut auth_id_storage = &mut self.auth_id_stroage.lock().unwrap();
+        //* Consumed here whatever the outcome (even a malformed s); an auth_id answers exactly one attempt.
+        let challenge = lock(&self.auth_id_stroage).remove(&aid);
 
-        if let Some(username) = auth_id_storage.get_mut(&aid) {
-            let mut user_info_storage = &mut self.user_info_storage.lock().unwrap();
-            //* Verify retrieved username
-            let user_info = user_info_storage.get_mut(username).expect("Not Found");
+        if let Some(challenge) = challenge {
+            if challenge.expires_at <= Instant::now() {
+                return Err(ZkpError::ChallengeExpired.into());
+            }
+            let s = self.zkp.parse_scalar(&req.s).map_err(|e| ZkpError::invalid("s", e))?;
 
-            let (alpha,beta,p,q) = ZKP::get_const();
-            let zkp = ZKP{alpha,beta,p,q};
+            //* Verify retrieved username; the user may be unknown or removed since the challenge was issued.
+            let (user_info, registered) = self.user_info(&challenge.username)?;
 
             //* Proceed verification.
-            let verification = zkp.verify(&user_info.r1, &user_info.r2, &user_info.y1, &user_info.y2, &user_info.c, &s);
-
-            if verification{
-                let session_id = ZKP::gen_rand_str(12);
-                Ok(Response::new(AuthenticationAnswerResponse{session_id}))
-            }else{
-                Err(Status::new(Code::PermissionDenied, format!("Permission Denied.")))
+            self.verify(&user_info, &challenge, &s)?;
+            if !registered {
+                return Err(ZkpError::VerificationFailed.into());
             }
+            Ok(Response::new(self.open_session(&challenge.username)))
+        }else {
+            Err(ZkpError::UnknownChallenge.into())
+        }
+    }
 
+    async fn authenticate_with_proof(&self, request: Request<AuthenticationProofRequest>) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let req = request.into_inner();
 
-        }else {
-            Err(Status::new(Code::NotFound, format!("Invalid Auth Id.")))
+        //* Storage
+        let (user_info, registered) = self.user_info(&req.username)?;
+
+        let transcript = self.zkp.login_transcript(&req.username, &self.server_id, req.timestamp);
+        self.check_proof(&user_info, &req.r1, &req.r2, &req.s, req.timestamp, &transcript)?;
+        if !registered {
+            return Err(ZkpError::VerificationFailed.into());
+        }
+
+        Ok(Response::new(self.open_session(&user_info.username)))
+    }
+
+    async fn validate_session(&self, request: Request<SessionRequest>) -> Result<Response<SessionResponse>, Status> {
+        let req = request.into_inner();
+
+        match self.session_storage.validate(&req.session_id) {
+            Some(session) => Ok(Response::new(session_to_msg(&session))),
+            None => Err(ZkpError::InvalidSession.into()),
+        }
+    }
+
+    async fn refresh_session(&self, request: Request<SessionRequest>) -> Result<Response<SessionResponse>, Status> {
+        let req = request.into_inner();
+
+        match self.session_storage.refresh(&req.session_id) {
+            Some(session) => Ok(Response::new(session_to_msg(&session))),
+            None => Err(ZkpError::InvalidSession.into()),
+        }
+    }
+
+    async fn logout(&self, request: Request<SessionRequest>) -> Result<Response<LogoutResponse>, Status> {
+        let req = request.into_inner();
+
+        if self.session_storage.revoke(&req.session_id) {
+            Ok(Response::new(LogoutResponse{}))
+        } else {
+            Err(ZkpError::UnknownSession.into())
+        }
+    }
+}
+
+//* Background sweeper; keeps the challenge storage bounded even if clients never answer.
+async fn sweep<G: Group>(auth_impl: Arc<AuthImpl<G>>) {
+    let mut interval = tokio::time::interval(auth_impl.challenge_ttl.max(Duration::from_secs(1)));
+    loop {
+        interval.tick().await;
+        let evicted = auth_impl.sweep_expired();
+        let expired = auth_impl.session_storage.sweep();
+        if evicted + expired > 0 {
+            println!("Evicted {} expired challenge(s), {} expired session(s)", evicted, expired);
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+async fn serve<G: Group>(zkp: ZKP<G>, beta_seed: Option<Vec<u8>>, server_id: String, kdf_floor: KdfParams, require_registration_proof: bool, user_store: Box<dyn UserStore>, challenge_ttl: Duration, session_ttl: Duration, addr: String) {
+    zkp.check_params().expect("Invalid group parameters");
+    //* Clients refuse a beta they can not re-derive; so does the server, before it takes any user.
+    let beta_seed = beta_seed.or(zkp.group.beta_seed()).expect("No seed for beta (ZKP_GROUP file without seed)");
+    if !zkp.check_beta(&beta_seed) {
+        panic!("beta is not derived from its seed");
+    }
+    //* alpha^s and beta^s of every verification come from the tables.
+    let zkp = zkp.with_tables();
+    println!("🎉🎉 Server is running on http://{} ({}, identity {})", addr, zkp.group.name(), server_id);
+
+    let auth_impl = Arc::new(AuthImpl{
+        beta_seed,
+        server_id,
+        require_registration_proof,
+        user_info_storage: user_store,
+        challenge_ttl,
+        session_storage: SessionStore::new(session_ttl),
+        ..AuthImpl::new(zkp, kdf_floor)
+    });
+    tokio::spawn(sweep(auth_impl.clone()));
+
+    Server::builder()
+        .add_service(AuthServiceServer::from_arc(auth_impl))
+        .serve(addr.parse().expect("Cannot convert addr"))
+        .await
+        .expect("Server stopped");
+}
+
 #[tokio::main] //* Async function
 async fn main(){
     let addr_local = "127.0.0.1:50051".to_string();
 
-    let auth_impl = AuthImpl::default(); //* Use Default Trait
+    //* Group backend; ZKP_GROUP=<named group> (default rfc5114-2048-256) | modp (legacy 1024-bit) | ristretto255
+    //*   | file:<path> (written by paramgen; validated before use)
+    let group = std::env::var("ZKP_GROUP").unwrap_or("rfc5114-2048-256".to_string());
+    let backend = Backend::from_name(&group).expect("Unknown ZKP_GROUP");
 
-    println!("🎉🎉 Server is running on http://{}", addr_local);
-    Server::builder()
-        .add_service(AuthServiceServer::new(auth_impl))
-        .serve(addr_local.parse().expect("Cannot convert addr"))
-        .await
-        .unwrap();
-}
\ No newline at end of file
+    //* KDF floor; ZKP_KDF_MEMORY_KIB, ZKP_KDF_ITERATIONS, ZKP_KDF_PARALLELISM (defaults: OWASP Argon2id)
+    let env_or = |name: &str, default: u32| std::env::var(name).map(|v| v.parse().expect(name)).unwrap_or(default);
+    let default_kdf = KdfParams::default();
+    let kdf_floor = KdfParams{
+        memory_kib: env_or("ZKP_KDF_MEMORY_KIB", default_kdf.memory_kib),
+        iterations: env_or("ZKP_KDF_ITERATIONS", default_kdf.iterations),
+        parallelism: env_or("ZKP_KDF_PARALLELISM", default_kdf.parallelism),
+        ..default_kdf
+    };
+
+    let secs_or = |name: &str, default: u64| Duration::from_secs(std::env::var(name).map(|v| v.parse().expect(name)).unwrap_or(default));
+    let challenge_ttl = secs_or("ZKP_CHALLENGE_TTL_SECS", CHALLENGE_TTL_SECS);
+    let session_ttl = secs_or("ZKP_SESSION_TTL_SECS", SESSION_TTL_SECS);
+
+    //* ZKP_REQUIRE_REGISTRATION_PROOF=1 rejects registrations and rotations without a proof of possession of the new key.
+    let require_registration_proof = std::env::var("ZKP_REQUIRE_REGISTRATION_PROOF").map(|v| v == "1").unwrap_or(false);
+
+    //* Identity bound into every challenge; ZKP_SERVER_ID (default: the listening address). Clients must use the same.
+    let server_id = std::env::var("ZKP_SERVER_ID").unwrap_or(SERVER_ID.to_string());
+
+    //* User storage; ZKP_STORE=memory (default) | file:<path> | sqlite:<path>
+    let store_spec = std::env::var("ZKP_STORE").unwrap_or("memory".to_string());
+    let user_store = store::open(&store_spec).expect("Cannot open ZKP_STORE");
+
+    match backend {
+        Backend::ModP => {
+            //* Named groups know their seed; paramgen files list it.
+            let (zkp, beta_seed) = if group == "modp" {
+                (ZKP::modp(), None)
+            } else if let Some(path) = group.strip_prefix("file:") {
+                let text = std::fs::read_to_string(path).expect("Cannot read ZKP_GROUP file");
+                let params = zkp_cp::GroupParams::parse(&text).expect("Cannot parse ZKP_GROUP file");
+                params.validate().expect("Invalid ZKP_GROUP file");
+                (params.zkp(), params.seed)
+            } else {
+                (ZKP::named(&group).expect("Unknown ZKP_GROUP"), None)
+            };
+            serve(zkp, beta_seed, server_id, kdf_floor, require_registration_proof, user_store, challenge_ttl, session_ttl, addr_local).await
+        }
+        Backend::Ristretto255 => serve(ZKP::ristretto255(), None, server_id, kdf_floor, require_registration_proof, user_store, challenge_ttl, session_ttl, addr_local).await,
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tonic::Code;
+    use crate::zkp_cp::SecretScalar;
+    use crate::zkp_auth::NonInteractiveProof;
+
+    fn server() -> AuthImpl<zkp_cp::ModPGroup> {
+        AuthImpl::new(ZKP::modp(), KdfParams::default())
+    }
+
+    fn server_with(user_store: Box<dyn UserStore>) -> AuthImpl<zkp_cp::ModPGroup> {
+        AuthImpl{ user_info_storage: user_store, ..server() }
+    }
+
+    //* One store spec per backend, inside dir.
+    fn store_specs(dir: &tempfile::TempDir) -> Vec<String> {
+        vec![
+            "memory".to_string(),
+            format!("file:{}", dir.path().join("users.log").display()),
+            format!("sqlite:{}", dir.path().join("users.db").display()),
+        ]
+    }
+
+    fn register_req(username: &str, y1: Vec<u8>, y2: Vec<u8>) -> RegisterRequest {
+        RegisterRequest{
+            username: username.to_string(),
+            y1,
+            y2,
+            salt: kdf::gen_salt(),
+            kdf: Some(kdf_to_msg(&KdfParams::default())),
+            proof: None,
+        }
+    }
+
+    fn proof_msg(auth: &AuthImpl<zkp_cp::ModPGroup>, proof: &Proof<zkp_cp::ModPGroup>) -> NonInteractiveProof {
+        NonInteractiveProof{
+            r1: auth.zkp.group.encode(&proof.r1),
+            r2: auth.zkp.group.encode(&proof.r2),
+            s: auth.zkp.group.encode_scalar(&proof.s),
+        }
+    }
+
+    //* Registers username with the secret x.
+    async fn register(auth: &AuthImpl<zkp_cp::ModPGroup>, username: &str, x: &SecretScalar) {
+        let (y1, y2) = auth.zkp.commit(x);
+        let req = register_req(username, auth.zkp.group.encode(&y1), auth.zkp.group.encode(&y2));
+        auth.register(Request::new(req)).await.unwrap();
+    }
+
+    //* Registers username with the secret x and its proof of possession.
+    async fn register_with_proof(auth: &AuthImpl<zkp_cp::ModPGroup>, username: &str, x: &SecretScalar) {
+        let (y1, y2) = auth.zkp.commit(x);
+        let req = RegisterRequest{
+            proof: Some(proof_msg(auth, &auth.zkp.prove_noninteractive(x, &auth.zkp.registration_transcript(username, &auth.server_id)))),
+            ..register_req(username, auth.zkp.group.encode(&y1), auth.zkp.group.encode(&y2))
+        };
+        auth.register(Request::new(req)).await.unwrap();
+    }
+
+    //* Sends (r1, r2) for the nonce k; returns (auth_id, c). c is checked the way the client does.
+    async fn challenge(auth: &AuthImpl<zkp_cp::ModPGroup>, username: &str, k: &SecretScalar) -> (String, BigUint) {
+        let (r1, r2) = auth.zkp.commit(k);
+        let challenge_req = AuthenticationChallengeRequest{
+            username: username.to_string(),
+            r1: auth.zkp.group.encode(&r1),
+            r2: auth.zkp.group.encode(&r2),
+        };
+        let c_resp = auth.create_authentication_challenge(Request::new(challenge_req)).await.unwrap().into_inner();
+        let c = auth.zkp.parse_scalar(&c_resp.c).unwrap();
+
+        let stored = auth.user_info_storage.get(username).unwrap().unwrap();
+        let (y1, y2) = (auth.zkp.parse_element(&stored.y1).unwrap(), auth.zkp.parse_element(&stored.y2).unwrap());
+        let transcript = auth.zkp.interactive_transcript(username, &auth.server_id, &c_resp.nonce);
+        assert_eq!(c, auth.zkp.challenge(&transcript, &y1, &y2, &r1, &r2));
+
+        (c_resp.auth_id, c)
+    }
+
+    async fn answer(auth: &AuthImpl<zkp_cp::ModPGroup>, auth_id: &str, s: &SecretScalar) -> Result<Response<AuthenticationAnswerResponse>, Status> {
+        let verify_req = AuthenticationAnswerRequest{ auth_id: auth_id.to_string(), s: auth.zkp.group.encode_scalar(&s.expose()) };
+        auth.verify_authentication(Request::new(verify_req)).await
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for spec in store_specs(&dir) {
+            let auth = server_with(store::open(&spec).unwrap());
+            let x = auth.zkp.random_secret();
+            register(&auth, "alice", &x).await;
+
+            let k = auth.zkp.random_secret();
+            let (auth_id, c) = challenge(&auth, "alice", &k).await;
+
+            let s = auth.zkp.solve(&k, &c, &x);
+            assert!(answer(&auth, &auth_id, &s).await.is_ok(), "{}", spec);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_proof_login_bound_to_server() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        let proof_req = |server_id: &str| {
+            let timestamp = unix_secs(SystemTime::now());
+            let proof = auth.zkp.prove_noninteractive(&x, &auth.zkp.login_transcript("alice", server_id, timestamp));
+            let msg = proof_msg(&auth, &proof);
+            AuthenticationProofRequest{ username: "alice".to_string(), r1: msg.r1, r2: msg.r2, s: msg.s, timestamp }
+        };
+
+        //* Proof made for another server
+        let err = auth.authenticate_with_proof(Request::new(proof_req("evil.example"))).await.unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        assert!(auth.authenticate_with_proof(Request::new(proof_req(&auth.server_id))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_proof_replay() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        let timestamp = unix_secs(SystemTime::now());
+        let proof = auth.zkp.prove_noninteractive(&x, &auth.zkp.login_transcript("alice", &auth.server_id, timestamp));
+        let msg = proof_msg(&auth, &proof);
+        let proof_req = |s: &[u8]| AuthenticationProofRequest{ username: "alice".to_string(), r1: msg.r1.clone(), r2: msg.r2.clone(), s: s.to_vec(), timestamp };
+
+        //* A forged s with the same r1 fails without marking r1; the real proof is accepted once.
+        let forged = auth.zkp.group.encode_scalar(&((&proof.s + 1u32) % auth.zkp.group.order()));
+        let err = auth.authenticate_with_proof(Request::new(proof_req(&forged))).await.unwrap_err();
+        assert_eq!(err.message(), "Permission Denied.");
+        assert!(auth.authenticate_with_proof(Request::new(proof_req(&msg.s))).await.is_ok());
+
+        let err = auth.authenticate_with_proof(Request::new(proof_req(&msg.s))).await.unwrap_err();
+        assert_eq!(err.message(), "Proof already used.");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_user_like_wrong_password() {
+        let auth = server();
+        register(&auth, "alice", &auth.zkp.random_secret()).await;
+        let wrong = auth.zkp.random_secret();
+
+        //* alice with a wrong password, bob not registered at all: same answers.
+        for username in ["alice", "bob"] {
+            let kdf_req = || Request::new(KdfParametersRequest{ username: username.to_string() });
+            let salt = auth.get_kdf_parameters(kdf_req()).await.unwrap().into_inner().salt;
+            assert_eq!(auth.get_kdf_parameters(kdf_req()).await.unwrap().into_inner().salt, salt);
+
+            let k = auth.zkp.random_secret();
+            let (r1, r2) = auth.zkp.commit(&k);
+            let challenge_req = AuthenticationChallengeRequest{
+                username: username.to_string(),
+                r1: auth.zkp.group.encode(&r1),
+                r2: auth.zkp.group.encode(&r2),
+            };
+            let c_resp = auth.create_authentication_challenge(Request::new(challenge_req)).await.unwrap().into_inner();
+            let c = auth.zkp.parse_scalar(&c_resp.c).unwrap();
+            let err = answer(&auth, &c_resp.auth_id, &auth.zkp.solve(&k, &c, &wrong)).await.unwrap_err();
+            assert_eq!((err.code(), err.message()), (Code::PermissionDenied, "Permission Denied."), "{}", username);
+
+            let timestamp = unix_secs(SystemTime::now());
+            let msg = proof_msg(&auth, &auth.zkp.prove_noninteractive(&wrong, &auth.zkp.login_transcript(username, &auth.server_id, timestamp)));
+            let proof_req = AuthenticationProofRequest{ username: username.to_string(), r1: msg.r1, r2: msg.r2, s: msg.s, timestamp };
+            let err = auth.authenticate_with_proof(Request::new(proof_req)).await.unwrap_err();
+            assert_eq!((err.code(), err.message()), (Code::PermissionDenied, "Permission Denied."), "{}", username);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_registration() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        //* Re-registering with other keys must not take the account over.
+        let (y1, y2) = auth.zkp.commit(&auth.zkp.random_secret());
+        let req = register_req("alice", auth.zkp.group.encode(&y1), auth.zkp.group.encode(&y2));
+        assert_eq!(auth.register(Request::new(req)).await.unwrap_err().code(), Code::AlreadyExists);
+
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        assert!(answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &x)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_registration_proof() {
+        let auth = AuthImpl{ require_registration_proof: true, ..server() };
+        let x = auth.zkp.random_secret();
+        let (y1, y2) = auth.zkp.commit(&x);
+        let (y1, y2) = (auth.zkp.group.encode(&y1), auth.zkp.group.encode(&y2));
+
+        //* Missing
+        let req = register_req("alice", y1.clone(), y2.clone());
+        assert_eq!(auth.register(Request::new(req)).await.unwrap_err().code(), Code::InvalidArgument);
+
+        //* y2 = beta^x' with x' != x; passes the element checks but not the proof.
+        let (_, other_y2) = auth.zkp.commit(&SecretScalar::new(x.expose() + 1u32));
+        let mixed = RegisterRequest{
+            proof: Some(proof_msg(&auth, &auth.zkp.prove_noninteractive(&x, &auth.zkp.registration_transcript("alice", &auth.server_id)))),
+            ..register_req("alice", y1.clone(), auth.zkp.group.encode(&other_y2))
+        };
+        assert_eq!(auth.register(Request::new(mixed)).await.unwrap_err().code(), Code::InvalidArgument);
+
+        //* Proof made for another username
+        let stolen = RegisterRequest{
+            proof: Some(proof_msg(&auth, &auth.zkp.prove_noninteractive(&x, &auth.zkp.registration_transcript("bob", &auth.server_id)))),
+            ..register_req("alice", y1.clone(), y2.clone())
+        };
+        assert_eq!(auth.register(Request::new(stolen)).await.unwrap_err().code(), Code::InvalidArgument);
+        assert!(!auth.user_info_storage.contains("alice").unwrap());
+
+        let req = RegisterRequest{
+            proof: Some(proof_msg(&auth, &auth.zkp.prove_noninteractive(&x, &auth.zkp.registration_transcript("alice", &auth.server_id)))),
+            ..register_req("alice", y1, y2)
+        };
+        auth.register(Request::new(req)).await.unwrap();
+    }
+
+    //* Rotation from old_x to new_x, proven with proof_x; new_key_x (if any) proves possession of the new key.
+    fn rotate_req(auth: &AuthImpl<zkp_cp::ModPGroup>, username: &str, proof_x: &SecretScalar, new_x: &SecretScalar, new_key_x: Option<&SecretScalar>) -> RotateCredentialsRequest {
+        let (y1, y2) = auth.zkp.commit(new_x);
+        let (y1, y2) = (auth.zkp.group.encode(&y1), auth.zkp.group.encode(&y2));
+        let salt = kdf::gen_salt();
+        let kdf = KdfParams::default();
+        let timestamp = unix_secs(SystemTime::now());
+
+        let new_proof = new_key_x.map(|x| auth.zkp.prove_noninteractive(x, &auth.zkp.rotation_key_transcript(username, &auth.server_id, timestamp)));
+        let transcript = auth.zkp.rotation_transcript(username, &auth.server_id, timestamp, &y1, &y2, &salt, &kdf, new_proof.as_ref());
+        let proof = auth.zkp.prove_noninteractive(proof_x, &transcript);
+
+        RotateCredentialsRequest{
+            username: username.to_string(),
+            r1: auth.zkp.group.encode(&proof.r1),
+            r2: auth.zkp.group.encode(&proof.r2),
+            s: auth.zkp.group.encode_scalar(&proof.s),
+            timestamp,
+            y1,
+            y2,
+            salt,
+            kdf: Some(kdf_to_msg(&kdf)),
+            new_proof: new_proof.map(|proof| proof_msg(auth, &proof)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotate_credentials() {
+        let auth = server();
+        let old_x = auth.zkp.random_secret();
+        let new_x = auth.zkp.random_secret();
+        register(&auth, "alice", &old_x).await;
+
+        //* Without the old secret
+        let forged = rotate_req(&auth, "alice", &new_x, &new_x, Some(&new_x));
+        assert_eq!(auth.rotate_credentials(Request::new(forged)).await.unwrap_err().code(), Code::PermissionDenied);
+
+        //* Proof bound to other credentials
+        let mut swapped = rotate_req(&auth, "alice", &old_x, &new_x, Some(&new_x));
+        swapped.salt = kdf::gen_salt();
+        assert_eq!(auth.rotate_credentials(Request::new(swapped)).await.unwrap_err().code(), Code::PermissionDenied);
+
+        let req = rotate_req(&auth, "alice", &old_x, &new_x, Some(&new_x));
+        auth.rotate_credentials(Request::new(req.clone())).await.unwrap();
+        //* Replay
+        assert_eq!(auth.rotate_credentials(Request::new(req)).await.unwrap_err().code(), Code::PermissionDenied);
+
+        //* Old secret is gone, new one works.
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        assert_eq!(answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &old_x)).await.unwrap_err().code(), Code::PermissionDenied);
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        assert!(answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &new_x)).await.is_ok());
+
+        let unknown = rotate_req(&auth, "bob", &old_x, &new_x, Some(&new_x));
+        assert_eq!(auth.rotate_credentials(Request::new(unknown)).await.unwrap_err().message(), "Permission Denied.");
+    }
+
+    #[tokio::test]
+    async fn test_rotation_proof() {
+        let auth = AuthImpl{ require_registration_proof: true, ..server() };
+        let old_x = auth.zkp.random_secret();
+        let new_x = auth.zkp.random_secret();
+        register_with_proof(&auth, "alice", &old_x).await;
+
+        //* Missing: a valid proof for the old key alone is not enough.
+        let missing = rotate_req(&auth, "alice", &old_x, &new_x, None);
+        assert_eq!(auth.rotate_credentials(Request::new(missing)).await.unwrap_err().code(), Code::InvalidArgument);
+
+        //* Proof for another x than the one behind the new y1, y2
+        let other = rotate_req(&auth, "alice", &old_x, &new_x, Some(&old_x));
+        assert_eq!(auth.rotate_credentials(Request::new(other)).await.unwrap_err().code(), Code::InvalidArgument);
+
+        let req = rotate_req(&auth, "alice", &old_x, &new_x, Some(&new_x));
+        assert!(auth.rotate_credentials(Request::new(req)).await.is_ok());
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        assert!(answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &new_x)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_registration_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+
+        //* The memory store forgets; only the persistent backends.
+        for spec in &store_specs(&dir)[1..] {
+            let x = {
+                let auth = server_with(store::open(spec).unwrap());
+                let x = auth.zkp.random_secret();
+                register(&auth, "alice", &x).await;
+                x
+            };
+
+            let auth = server_with(store::open(spec).unwrap());
+            let kdf_req = KdfParametersRequest{ username: "alice".to_string() };
+            let stored = auth.user_info_storage.get("alice").unwrap().unwrap();
+            assert_eq!(auth.get_kdf_parameters(Request::new(kdf_req)).await.unwrap().into_inner().salt, stored.salt);
+
+            let k = auth.zkp.random_secret();
+            let (auth_id, c) = challenge(&auth, "alice", &k).await;
+            assert!(answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &x)).await.is_ok(), "{}", spec);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_parameters() {
+        let auth = server();
+        let params = auth.get_parameters(Request::new(ParametersRequest{})).await.unwrap().into_inner();
+
+        assert_eq!(params.group, "rfc5114-1024-160");
+        assert_eq!(params.protocol_version, PROTOCOL_VERSION);
+        //* Enough to rebuild the same group on the other side
+        let zkp = ZKP::from_modp(
+            BigUint::from_bytes_be(&params.p),
+            BigUint::from_bytes_be(&params.q),
+            BigUint::from_bytes_be(&params.alpha),
+            BigUint::from_bytes_be(&params.beta),
+        ).unwrap();
+        assert_eq!((&zkp.group.p, &zkp.group.q, &zkp.group.g), (&auth.zkp.group.p, &auth.zkp.group.q, &auth.zkp.group.g));
+        assert_eq!(zkp.beta, auth.zkp.beta);
+        //* beta can be re-derived from what is served
+        assert_eq!(params.beta_seed, zkp_chaum_pedersen::params::beta_seed("rfc5114-1024-160"));
+        assert!(zkp.check_beta(&params.beta_seed));
+
+        //* Registry groups go by their registry name, not by the size of p.
+        let auth = AuthImpl::new(ZKP::named("rfc5114-2048-224").unwrap(), KdfParams::default());
+        let params = auth.get_parameters(Request::new(ParametersRequest{})).await.unwrap().into_inner();
+        assert_eq!(params.group, "rfc5114-2048-224");
+    }
+
+    #[tokio::test]
+    async fn test_session() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        let login = answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &x)).await.unwrap().into_inner();
+        let session_req = || Request::new(SessionRequest{ session_id: login.session_id.clone() });
+
+        //* Downstream service asks who this is
+        let session = auth.validate_session(session_req()).await.unwrap().into_inner();
+        assert_eq!(session.username, "alice");
+        assert_eq!(session.expires_at, login.expires_at);
+
+        let refreshed = auth.refresh_session(session_req()).await.unwrap().into_inner();
+        assert!(refreshed.expires_at >= session.expires_at);
+
+        auth.logout(session_req()).await.unwrap();
+        assert_eq!(auth.validate_session(session_req()).await.unwrap_err().code(), Code::Unauthenticated);
+        assert_eq!(auth.refresh_session(session_req()).await.unwrap_err().code(), Code::Unauthenticated);
+        assert_eq!(auth.logout(session_req()).await.unwrap_err().code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_logins() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        //* Both challenges are issued before either is answered.
+        let (k1, k2) = (auth.zkp.random_secret(), auth.zkp.random_secret());
+        let (auth_id1, c1) = challenge(&auth, "alice", &k1).await;
+        let (auth_id2, c2) = challenge(&auth, "alice", &k2).await;
+        assert_ne!(auth_id1, auth_id2);
+
+        //* Answered in reverse order
+        assert!(answer(&auth, &auth_id2, &auth.zkp.solve(&k2, &c2, &x)).await.is_ok());
+        assert!(answer(&auth, &auth_id1, &auth.zkp.solve(&k1, &c1, &x)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_challenge_consumed_once() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        let s = auth.zkp.solve(&k, &c, &x);
+
+        assert!(answer(&auth, &auth_id, &s).await.is_ok());
+        //* Replaying the same answer
+        assert_eq!(answer(&auth, &auth_id, &s).await.unwrap_err().code(), Code::NotFound);
+        assert!(auth.auth_id_stroage.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_register_rejects_degenerate_keys() {
+        let auth = server();
+        let (y1, y2) = auth.zkp.commit(&auth.zkp.random_secret());
+        let y1 = auth.zkp.group.encode(&y1);
+        let y2 = auth.zkp.group.encode(&y2);
+        let encode = |n: BigUint| auth.zkp.group.encode(&n);
+        let p_minus_one = encode(&auth.zkp.group.p - 1u32);
+
+        for (bad_y1, bad_y2) in [
+            (encode(BigUint::from(1u32)), y2.clone()), //* identity
+            (y1.clone(), encode(BigUint::from(0u32))), //* zero
+            (auth.zkp.group.p.to_bytes_be(), y2.clone()), //* p
+            (p_minus_one, y2.clone()), //* order 2
+            ([&[0u8][..], &y1].concat(), y2.clone()), //* leading zero
+        ] {
+            let status = auth.register(Request::new(register_req("mallory", bad_y1, bad_y2))).await.unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+        }
+
+        //* Nothing stored
+        assert!(!auth.user_info_storage.contains("mallory").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_commitment_and_response() {
+        let auth = server();
+        register(&auth, "alice", &auth.zkp.random_secret()).await;
+
+        //* r1 = 1
+        let (_, r2) = auth.zkp.commit(&auth.zkp.random_secret());
+        let challenge_req = AuthenticationChallengeRequest{
+            username: "alice".to_string(),
+            r1: auth.zkp.group.encode(&BigUint::from(1u32)),
+            r2: auth.zkp.group.encode(&r2),
+        };
+        let status = auth.create_authentication_challenge(Request::new(challenge_req)).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        //* s = q
+        let (auth_id, _) = challenge(&auth, "alice", &auth.zkp.random_secret()).await;
+        let q = auth.zkp.group.q.clone();
+        assert_eq!(answer(&auth, &auth_id, &SecretScalar::new(q)).await.unwrap_err().code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_failed_attempt_consumes_challenge() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+
+        //* Wrong answer, then the right one: the auth_id is already gone.
+        let wrong = auth.zkp.solve(&k, &c, &SecretScalar::new(x.expose() + 1u32));
+        assert_eq!(answer(&auth, &auth_id, &wrong).await.unwrap_err().code(), Code::PermissionDenied);
+        let s = auth.zkp.solve(&k, &c, &x);
+        assert_eq!(answer(&auth, &auth_id, &s).await.unwrap_err().code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_challenge_expiry() {
+        let auth = AuthImpl{ challenge_ttl: Duration::ZERO, ..server() };
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        //* Expired on arrival
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        let s = auth.zkp.solve(&k, &c, &x);
+        assert_eq!(answer(&auth, &auth_id, &s).await.unwrap_err().code(), Code::PermissionDenied);
+
+        //* Never answered; the sweeper evicts them.
+        challenge(&auth, "alice", &k).await;
+        challenge(&auth, "alice", &k).await;
+        assert_eq!(auth.sweep_expired(), 2);
+        assert!(auth.auth_id_stroage.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_survives_poisoned_lock() {
+        let auth = server();
+        let x = auth.zkp.random_secret();
+        register(&auth, "alice", &x).await;
+
+        //* A handler panics while holding the challenge and proof storages.
+        std::thread::scope(|scope| {
+            let poisoned = scope.spawn(|| {
+                let _challenges = auth.auth_id_stroage.lock().unwrap();
+                let _proofs = auth.used_proof_storage.lock().unwrap();
+                panic!("handler bug");
+            });
+            assert!(poisoned.join().is_err());
+        });
+        assert!(auth.auth_id_stroage.is_poisoned());
+
+        //* Later requests are still served.
+        let k = auth.zkp.random_secret();
+        let (auth_id, c) = challenge(&auth, "alice", &k).await;
+        assert!(answer(&auth, &auth_id, &auth.zkp.solve(&k, &c, &x)).await.is_ok());
+        auth.sweep_expired();
+    }
+}
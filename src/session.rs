@@ -0,0 +1,164 @@
+// Sessions issued after a successful proof.
+// Downstream services hand the session id back to the server to learn who is logged in.
+
+// - create(): after VerifyAuthentication / AuthenticateWithProof
+// - validate(): session still alive -> owner
+// - refresh(): push the expiry another TTL forward
+// - revoke(): logout
+// - sweep(): drop expired sessions (background task)
+
+use std::{collections::HashMap, sync::Mutex, time::{Duration, SystemTime}};
+use crate::error::lock;
+use crate::zkp_cp::ZKP;
+
+//* 32 alphanumerics, ~190 bits; the session id is a bearer token.
+const SESSION_ID_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub session_id: String,
+    pub username: String,
+    pub created_at: SystemTime,
+    pub expires_at: SystemTime,
+}
+
+impl Session {
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at <= now
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionStore {
+    pub ttl: Duration,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        SessionStore {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn create(&self, username: &str) -> Session {
+        let now = SystemTime::now();
+        let session = Session {
+            session_id: ZKP::gen_rand_str(SESSION_ID_LEN),
+            username: username.to_string(),
+            created_at: now,
+            expires_at: now + self.ttl,
+        };
+
+        lock(&self.sessions).insert(session.session_id.clone(), session.clone());
+        session
+    }
+
+    //* None if unknown or expired; an expired session is dropped on the spot.
+    pub fn validate(&self, session_id: &str) -> Option<Session> {
+        let sessions = &mut lock(&self.sessions);
+
+        match sessions.get(session_id) {
+            Some(session) if !session.is_expired(SystemTime::now()) => Some(session.clone()),
+            Some(_) => {
+                sessions.remove(session_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    //* New expiry = now + ttl; expired sessions can not be revived.
+    pub fn refresh(&self, session_id: &str) -> Option<Session> {
+        let sessions = &mut lock(&self.sessions);
+        let now = SystemTime::now();
+
+        match sessions.get_mut(session_id) {
+            Some(session) if !session.is_expired(now) => {
+                session.expires_at = now + self.ttl;
+                Some(session.clone())
+            }
+            Some(_) => {
+                sessions.remove(session_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    //* true if the session existed (expired or not).
+    pub fn revoke(&self, session_id: &str) -> bool {
+        lock(&self.sessions).remove(session_id).is_some()
+    }
+
+    //* Returns the number of evicted sessions.
+    pub fn sweep(&self) -> usize {
+        let sessions = &mut lock(&self.sessions);
+        let now = SystemTime::now();
+        let before = sessions.len();
+        sessions.retain(|_, session| !session.is_expired(now));
+        before - sessions.len()
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.sessions).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_session_lifecycle() {
+        let store = SessionStore::new(Duration::from_secs(60));
+
+        let session = store.create("alice");
+        assert_eq!(session.session_id.len(), SESSION_ID_LEN);
+        assert_eq!(store.validate(&session.session_id), Some(session.clone()));
+
+        //* Refresh moves the expiry forward, never backward.
+        let refreshed = store.refresh(&session.session_id).unwrap();
+        assert_eq!(refreshed.username, "alice");
+        assert!(refreshed.expires_at >= session.expires_at);
+
+        //* Logout
+        assert!(store.revoke(&session.session_id));
+        assert!(!store.revoke(&session.session_id));
+        assert_eq!(store.validate(&session.session_id), None);
+        assert_eq!(store.refresh(&session.session_id), None);
+    }
+
+    #[test]
+    fn test_session_ids_unique() {
+        let store = SessionStore::new(Duration::from_secs(60));
+        let a = store.create("alice");
+        let b = store.create("alice");
+
+        assert_ne!(a.session_id, b.session_id);
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_session_expiry() {
+        let store = SessionStore::new(Duration::ZERO);
+
+        let session = store.create("alice");
+        assert_eq!(store.refresh(&session.session_id), None);
+        assert_eq!(store.validate(&session.session_id), None);
+
+        store.create("bob");
+        store.create("carol");
+        assert_eq!(store.sweep(), 2);
+        assert!(store.is_empty());
+    }
+}
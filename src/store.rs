@@ -0,0 +1,490 @@
+// Registered users (username -> y1, y2, salt, KDF costs).
+// The server only talks to the UserStore trait; the backend is picked at startup.
+
+// Backends
+// - MemoryStore: HashMap; lost on restart (tests, demos)
+// - FileStore: append-only log, replayed on open; the last record of a username wins
+// - SqliteStore: embedded SQLite database
+
+use std::{collections::HashMap, fmt, fs::{File, OpenOptions}, io::{self, BufRead, BufReader, Write}, path::Path, sync::Mutex};
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::error::lock;
+use crate::kdf::KdfParams;
+
+//* Structure for user information
+//* Group elements are kept in their wire encoding; the group decodes them on verification.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserInfo {
+    pub username: String,
+
+    //* Registration
+    pub y1: Vec<u8>,
+    pub y2: Vec<u8>,
+    pub salt: Vec<u8>,
+    pub kdf: KdfParams,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    Corrupt(String), //* A stored record could not be parsed.
+    UnknownBackend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "io: {}", e),
+            StoreError::Sqlite(e) => write!(f, "sqlite: {}", e),
+            StoreError::Corrupt(reason) => write!(f, "corrupt record: {}", reason),
+            StoreError::UnknownBackend(spec) => write!(f, "unknown store backend: {}", spec),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Sqlite(e)
+    }
+}
+
+pub trait UserStore: Send + Sync {
+    fn get(&self, username: &str) -> Result<Option<UserInfo>, StoreError>;
+
+    //* Insert, or replace the user with the same username.
+    fn put(&self, user: UserInfo) -> Result<(), StoreError>;
+
+    //* Insert only if the username is free; false if it is taken. Atomic (registration).
+    fn insert(&self, user: UserInfo) -> Result<bool, StoreError>;
+
+    fn contains(&self, username: &str) -> Result<bool, StoreError> {
+        Ok(self.get(username)?.is_some())
+    }
+}
+
+//* Backend from a spec string: "memory" | "file:<path>" | "sqlite:<path>"
+pub fn open(spec: &str) -> Result<Box<dyn UserStore>, StoreError> {
+    if spec == "memory" {
+        return Ok(Box::new(MemoryStore::default()));
+    }
+    match spec.split_once(':') {
+        Some(("file", path)) => Ok(Box::new(FileStore::open(path)?)),
+        Some(("sqlite", path)) => Ok(Box::new(SqliteStore::open(path)?)),
+        _ => Err(StoreError::UnknownBackend(spec.to_string())),
+    }
+}
+
+
+//* Memory
+//==========================
+
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    users: Mutex<HashMap<String, UserInfo>>,
+}
+
+impl UserStore for MemoryStore {
+    fn get(&self, username: &str) -> Result<Option<UserInfo>, StoreError> {
+        Ok(lock(&self.users).get(username).cloned())
+    }
+
+    fn put(&self, user: UserInfo) -> Result<(), StoreError> {
+        lock(&self.users).insert(user.username.clone(), user);
+        Ok(())
+    }
+
+    fn insert(&self, user: UserInfo) -> Result<bool, StoreError> {
+        let users = &mut lock(&self.users);
+        if users.contains_key(&user.username) {
+            return Ok(false);
+        }
+        users.insert(user.username.clone(), user);
+        Ok(true)
+    }
+}
+
+
+//* Append-only file
+//==========================
+
+// One record per line, tab separated:
+// v1 <username hex> <y1 hex> <y2 hex> <salt hex> <kdf version> <memory_kib> <iterations> <parallelism>
+// A torn last line (crash while appending) is cut off on open; a failed append is cut off right away;
+// any other bad line is an error.
+
+const RECORD_VERSION: &str = "v1";
+
+#[derive(Debug)]
+pub struct FileStore {
+    //* File handle and the replayed view of it; one lock so that both stay in step.
+    inner: Mutex<(File, HashMap<String, UserInfo>)>,
+}
+
+impl FileStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        let mut users = HashMap::new();
+        //* Length of the log up to the last complete record.
+        let mut valid_len = 0u64;
+
+        if path.as_ref().exists() {
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut line = Vec::new();
+            let mut line_no = 0;
+
+            while reader.read_until(b'\n', &mut line)? > 0 {
+                line_no += 1;
+                let complete = line.last() == Some(&b'\n');
+
+                match FileStore::parse(line.strip_suffix(b"\n").unwrap_or(&line)) {
+                    Some(user) if complete => {
+                        valid_len += line.len() as u64;
+                        users.insert(user.username.clone(), user);
+                    }
+                    _ if !complete => break, //* torn write; only the last line can be unterminated
+                    _ => return Err(StoreError::Corrupt(format!("line {}", line_no))),
+                }
+                line.clear();
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        //* Drop a torn last record so the next one starts on its own line.
+        if file.metadata()?.len() > valid_len {
+            file.set_len(valid_len)?;
+        }
+
+        Ok(FileStore { inner: Mutex::new((file, users)) })
+    }
+
+    fn format(user: &UserInfo) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            RECORD_VERSION,
+            hex::encode(user.username.as_bytes()),
+            hex::encode(&user.y1),
+            hex::encode(&user.y2),
+            hex::encode(&user.salt),
+            user.kdf.version,
+            user.kdf.memory_kib,
+            user.kdf.iterations,
+            user.kdf.parallelism,
+        )
+    }
+
+    //* Durable before it is visible.
+    fn append(file: &mut File, users: &mut HashMap<String, UserInfo>, user: UserInfo) -> Result<(), StoreError> {
+        FileStore::append_with(file, users, user, |file, record| {
+            file.write_all(record)?;
+            file.sync_data()
+        })
+    }
+
+    //* A failed write is cut back off; left in place, the next append would land after it
+    //* and the half record would sit in the middle of the log, where open() refuses it.
+    fn append_with(
+        file: &mut File,
+        users: &mut HashMap<String, UserInfo>,
+        user: UserInfo,
+        write: impl FnOnce(&mut File, &[u8]) -> io::Result<()>,
+    ) -> Result<(), StoreError> {
+        let len = file.metadata()?.len();
+        if let Err(e) = write(file, FileStore::format(&user).as_bytes()) {
+            file.set_len(len)?;
+            return Err(e.into());
+        }
+
+        users.insert(user.username.clone(), user);
+        Ok(())
+    }
+
+    //* One record, without the trailing newline.
+    fn parse(line: &[u8]) -> Option<UserInfo> {
+        let line = std::str::from_utf8(line).ok()?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 9 || fields[0] != RECORD_VERSION {
+            return None;
+        }
+
+        Some(UserInfo {
+            username: String::from_utf8(hex::decode(fields[1]).ok()?).ok()?,
+            y1: hex::decode(fields[2]).ok()?,
+            y2: hex::decode(fields[3]).ok()?,
+            salt: hex::decode(fields[4]).ok()?,
+            kdf: KdfParams {
+                version: fields[5].parse().ok()?,
+                memory_kib: fields[6].parse().ok()?,
+                iterations: fields[7].parse().ok()?,
+                parallelism: fields[8].parse().ok()?,
+            },
+        })
+    }
+}
+
+impl UserStore for FileStore {
+    fn get(&self, username: &str) -> Result<Option<UserInfo>, StoreError> {
+        Ok(lock(&self.inner).1.get(username).cloned())
+    }
+
+    fn put(&self, user: UserInfo) -> Result<(), StoreError> {
+        let (file, users) = &mut *lock(&self.inner);
+        FileStore::append(file, users, user)
+    }
+
+    fn insert(&self, user: UserInfo) -> Result<bool, StoreError> {
+        let (file, users) = &mut *lock(&self.inner);
+        if users.contains_key(&user.username) {
+            return Ok(false);
+        }
+        FileStore::append(file, users, user)?;
+        Ok(true)
+    }
+}
+
+
+//* SQLite
+//==========================
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StoreError> {
+        SqliteStore::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        SqliteStore::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, StoreError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS users (
+                username TEXT PRIMARY KEY,
+                y1 BLOB NOT NULL,
+                y2 BLOB NOT NULL,
+                salt BLOB NOT NULL,
+                kdf_version INTEGER NOT NULL,
+                kdf_memory_kib INTEGER NOT NULL,
+                kdf_iterations INTEGER NOT NULL,
+                kdf_parallelism INTEGER NOT NULL
+            );",
+        )?;
+
+        Ok(SqliteStore { conn: Mutex::new(conn) })
+    }
+
+    //* verb: "INSERT OR REPLACE" | "INSERT OR IGNORE"; returns the number of changed rows.
+    fn write(&self, verb: &str, user: &UserInfo) -> Result<usize, StoreError> {
+        let conn = lock(&self.conn);
+        let changed = conn.execute(
+            &format!(
+                "{} INTO users
+                 (username, y1, y2, salt, kdf_version, kdf_memory_kib, kdf_iterations, kdf_parallelism)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                verb,
+            ),
+            params![
+                user.username,
+                user.y1,
+                user.y2,
+                user.salt,
+                user.kdf.version,
+                user.kdf.memory_kib,
+                user.kdf.iterations,
+                user.kdf.parallelism,
+            ],
+        )?;
+
+        Ok(changed)
+    }
+}
+
+impl UserStore for SqliteStore {
+    fn get(&self, username: &str) -> Result<Option<UserInfo>, StoreError> {
+        let conn = lock(&self.conn);
+        let user = conn
+            .query_row(
+                "SELECT username, y1, y2, salt, kdf_version, kdf_memory_kib, kdf_iterations, kdf_parallelism
+                 FROM users WHERE username = ?1",
+                params![username],
+                |row| {
+                    Ok(UserInfo {
+                        username: row.get(0)?,
+                        y1: row.get(1)?,
+                        y2: row.get(2)?,
+                        salt: row.get(3)?,
+                        kdf: KdfParams {
+                            version: row.get(4)?,
+                            memory_kib: row.get(5)?,
+                            iterations: row.get(6)?,
+                            parallelism: row.get(7)?,
+                        },
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(user)
+    }
+
+    fn put(&self, user: UserInfo) -> Result<(), StoreError> {
+        self.write("INSERT OR REPLACE", &user)?;
+        Ok(())
+    }
+
+    fn insert(&self, user: UserInfo) -> Result<bool, StoreError> {
+        //* The primary key makes this atomic; a taken username changes no row.
+        Ok(self.write("INSERT OR IGNORE", &user)? == 1)
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn user(username: &str, y: u8) -> UserInfo {
+        UserInfo {
+            username: username.to_string(),
+            y1: vec![y, 1],
+            y2: vec![y, 2],
+            salt: vec![y; 16],
+            kdf: KdfParams::default(),
+        }
+    }
+
+    //* Shared suite; every backend has to pass this.
+    fn check_store(store: &dyn UserStore) {
+        assert_eq!(store.get("alice").unwrap(), None);
+        assert!(!store.contains("alice").unwrap());
+
+        store.put(user("alice", 1)).unwrap();
+        store.put(user("bob", 2)).unwrap();
+        assert_eq!(store.get("alice").unwrap(), Some(user("alice", 1)));
+        assert_eq!(store.get("bob").unwrap(), Some(user("bob", 2)));
+        assert!(store.contains("alice").unwrap());
+
+        //* Replace
+        store.put(user("alice", 3)).unwrap();
+        assert_eq!(store.get("alice").unwrap(), Some(user("alice", 3)));
+
+        //* Insert never replaces
+        assert!(!store.insert(user("alice", 5)).unwrap());
+        assert_eq!(store.get("alice").unwrap(), Some(user("alice", 3)));
+        assert!(store.insert(user("carol", 5)).unwrap());
+        assert_eq!(store.get("carol").unwrap(), Some(user("carol", 5)));
+
+        //* Usernames are opaque; separators and unicode are fine.
+        store.put(user("tab\tnew\nline 🦀", 4)).unwrap();
+        assert_eq!(store.get("tab\tnew\nline 🦀").unwrap(), Some(user("tab\tnew\nline 🦀", 4)));
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(&MemoryStore::default());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+
+        check_store(&FileStore::open(&path).unwrap());
+
+        //* Survives a restart
+        let reopened = FileStore::open(&path).unwrap();
+        assert_eq!(reopened.get("alice").unwrap(), Some(user("alice", 3)));
+        assert_eq!(reopened.get("bob").unwrap(), Some(user("bob", 2)));
+    }
+
+    #[test]
+    fn test_file_store_torn_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+
+        FileStore::open(&path).unwrap().put(user("alice", 1)).unwrap();
+        //* Crash in the middle of the next append
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"v1\t626f62\t0201").unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        assert_eq!(store.get("alice").unwrap(), Some(user("alice", 1)));
+        assert_eq!(store.get("bob").unwrap(), None);
+
+        //* Appends after the torn line still replay.
+        store.put(user("bob", 2)).unwrap();
+        assert_eq!(FileStore::open(&path).unwrap().get("bob").unwrap(), Some(user("bob", 2)));
+    }
+
+    #[test]
+    fn test_file_store_failed_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+        let store = FileStore::open(&path).unwrap();
+        store.put(user("alice", 1)).unwrap();
+
+        //* Disk full halfway through the record
+        {
+            let (file, users) = &mut *lock(&store.inner);
+            let failed = FileStore::append_with(file, users, user("bob", 2), |file, record| {
+                file.write_all(&record[..record.len() / 2])?;
+                Err(io::Error::other("disk full"))
+            });
+            assert!(matches!(failed, Err(StoreError::Io(_))));
+        }
+        assert_eq!(store.get("bob").unwrap(), None);
+
+        //* The next append starts on a clean line and the log still opens.
+        store.put(user("carol", 3)).unwrap();
+        let reopened = FileStore::open(&path).unwrap();
+        assert_eq!(reopened.get("alice").unwrap(), Some(user("alice", 1)));
+        assert_eq!(reopened.get("bob").unwrap(), None);
+        assert_eq!(reopened.get("carol").unwrap(), Some(user("carol", 3)));
+    }
+
+    #[test]
+    fn test_file_store_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.log");
+
+        std::fs::write(&path, "garbage\n".to_string() + &FileStore::format(&user("alice", 1))).unwrap();
+        assert!(matches!(FileStore::open(&path), Err(StoreError::Corrupt(_))));
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.db");
+
+        check_store(&SqliteStore::open(&path).unwrap());
+        check_store(&SqliteStore::open_in_memory().unwrap());
+
+        //* Survives a restart
+        let reopened = SqliteStore::open(&path).unwrap();
+        assert_eq!(reopened.get("alice").unwrap(), Some(user("alice", 3)));
+    }
+
+    #[test]
+    fn test_open_spec() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert!(open("memory").is_ok());
+        assert!(open(&format!("file:{}", dir.path().join("a.log").display())).is_ok());
+        assert!(open(&format!("sqlite:{}", dir.path().join("a.db").display())).is_ok());
+        assert!(matches!(open("redis:localhost"), Err(StoreError::UnknownBackend(_))));
+    }
+}
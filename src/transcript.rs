@@ -0,0 +1,199 @@
+// Merlin transcripts (STROBE-128) for every challenge of the protocol, interactive and Fiat-Shamir.
+// Absorbed in this order; c depends on all of it:
+
+// - protocol label (PROTOCOL_LABEL) and flow (login, register, rotate, rotate-key, interactive)
+// - group id: encode_params(), alpha, beta
+// - username, server identity
+// - data of the flow: timestamp, new credentials and their proof (rotate), verifier nonce (interactive)
+// - statement y1, y2 and commitment r1, r2
+
+// Merlin frames and labels every message. A proof made for another server, user, group or flow
+// gets another c and does not verify there.
+// Interactive: the verifier still picks c, by absorbing a random nonce of its own. It sends the nonce with c;
+// the prover recomputes c and never answers a challenge bound to another server or user.
+
+use merlin::Transcript;
+use num_bigint::BigUint;
+use rand::RngCore;
+use crate::group::Group;
+use crate::zkp_cp::ZKP;
+
+pub const PROTOCOL_LABEL: &[u8] = b"zkp_chaum_pedersen/v2";
+
+pub const VERIFIER_NONCE_LEN: usize = 32;
+
+//* Every flow gets its own transcripts; a login proof never verifies as a rotation proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Login, //* One-shot login (AuthenticateWithProof)
+    Register, //* Proof of possession at registration
+    Rotate, //* Credential rotation
+    RotateKey, //* Proof of possession of the new key at rotation
+    Interactive, //* Challenge and answer
+}
+
+impl Flow {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Flow::Login => b"login",
+            Flow::Register => b"register",
+            Flow::Rotate => b"rotate",
+            Flow::RotateKey => b"rotate-key",
+            Flow::Interactive => b"interactive",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProtocolTranscript(Transcript);
+
+impl ProtocolTranscript {
+    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.0.append_message(label, message);
+    }
+
+    pub fn append_u64(&mut self, label: &'static [u8], n: u64) {
+        self.0.append_u64(label, n);
+    }
+
+    //* Squeezing changes the state; the next challenge depends on this one.
+    pub fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.0.challenge_bytes(label, dest);
+    }
+
+    //* Digest of everything absorbed so far (nonce context); the transcript is unchanged.
+    pub fn fingerprint(&self) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        self.clone().challenge_bytes(b"fingerprint", &mut out);
+        out
+    }
+}
+
+//* Random nonce of the verifier for one interactive challenge.
+pub fn verifier_nonce() -> Vec<u8> {
+    let mut nonce = vec![0u8; VERIFIER_NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+impl<G: Group> ZKP<G> {
+    //* transcript
+    // Transcript of one flow, bound to the parameters, the user and the server.
+    pub fn transcript(&self, flow: Flow, username: &str, server_id: &str) -> ProtocolTranscript {
+        let mut transcript = ProtocolTranscript(Transcript::new(PROTOCOL_LABEL));
+        transcript.append_message(b"flow", flow.label());
+
+        for param in self.group.encode_params() {
+            transcript.append_message(b"group", &param);
+        }
+        transcript.append_message(b"alpha", &self.group.encode(&self.alpha));
+        transcript.append_message(b"beta", &self.group.encode(&self.beta));
+
+        transcript.append_message(b"username", username.as_bytes());
+        transcript.append_message(b"server", server_id.as_bytes());
+        transcript
+    }
+
+    //* Interactive login; the verifier draws the nonce, the prover gets it with c.
+    pub fn interactive_transcript(&self, username: &str, server_id: &str, nonce: &[u8]) -> ProtocolTranscript {
+        let mut transcript = self.transcript(Flow::Interactive, username, server_id);
+        transcript.append_message(b"verifier-nonce", nonce);
+        transcript
+    }
+
+    //* challenge
+    // output = c = (64 bytes squeezed after y1, y2, r1, r2) mod q; the transcript itself is not changed.
+    pub fn challenge(
+        &self,
+        transcript: &ProtocolTranscript,
+        y1: &G::Element,
+        y2: &G::Element,
+        r1: &G::Element,
+        r2: &G::Element,
+    ) -> BigUint {
+        let mut transcript = transcript.clone();
+
+        //* Statement and commitment
+        for (label, e) in [(b"y1", y1), (b"y2", y2), (b"r1", r1), (b"r2", r2)] {
+            transcript.append_message(label, &self.group.encode(e));
+        }
+
+        let mut c = [0u8; 64];
+        transcript.challenge_bytes(b"c", &mut c);
+        BigUint::from_bytes_be(&c) % self.group.order()
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::zkp_cp::SecretScalar;
+
+    fn check_domain_separation<G: Group>(zkp: &ZKP<G>) {
+        let ((y1, y2), (r1, r2)) = (zkp.commit(&BigUint::from(6u32).into()), zkp.commit(&BigUint::from(7u32).into()));
+        let transcript = zkp.transcript(Flow::Login, "alice", "auth.example");
+        let c = zkp.challenge(&transcript, &y1, &y2, &r1, &r2);
+
+        //* Deterministic, in range, transcript unchanged
+        assert_eq!(c, zkp.challenge(&transcript, &y1, &y2, &r1, &r2));
+        assert!(c < *zkp.group.order());
+        assert_eq!(transcript.fingerprint(), zkp.transcript(Flow::Login, "alice", "auth.example").fingerprint());
+
+        //* Every absorbed value changes c
+        let others = [
+            zkp.transcript(Flow::Register, "alice", "auth.example"),
+            zkp.transcript(Flow::Login, "bob", "auth.example"),
+            zkp.transcript(Flow::Login, "alice", "evil.example"),
+            zkp.transcript(Flow::Login, "alicea", "uth.example"),
+            zkp.interactive_transcript("alice", "auth.example", &[0u8; VERIFIER_NONCE_LEN]),
+        ];
+        for other in &others {
+            assert_ne!(c, zkp.challenge(other, &y1, &y2, &r1, &r2));
+        }
+        let mut appended = transcript.clone();
+        appended.append_u64(b"timestamp", 1700000000);
+        assert_ne!(c, zkp.challenge(&appended, &y1, &y2, &r1, &r2));
+
+        //* Statement and commitment: swapped y and r, swapped r1 and r2
+        assert_ne!(c, zkp.challenge(&transcript, &r1, &r2, &y1, &y2));
+        assert_ne!(c, zkp.challenge(&transcript, &y1, &y2, &r2, &r1));
+    }
+
+    #[test]
+    fn test_domain_separation_modp() {
+        check_domain_separation(&ZKP::modp());
+    }
+
+    #[test]
+    fn test_domain_separation_ristretto255() {
+        check_domain_separation(&ZKP::ristretto255());
+    }
+
+    #[test]
+    fn test_group_id() {
+        //* Same username, server and flow; other parameters
+        let (zkp, other) = (ZKP::modp(), ZKP::named("rfc5114-2048-256").unwrap());
+        let fingerprint = |zkp: &ZKP| zkp.transcript(Flow::Login, "alice", "auth.example").fingerprint();
+        assert_ne!(fingerprint(&zkp), fingerprint(&other));
+        assert_ne!(fingerprint(&zkp), fingerprint(&ZKP { beta: zkp.pow(&zkp.beta, &BigUint::from(2u32)), ..ZKP::modp() }));
+    }
+
+    #[test]
+    fn test_interactive_challenge() {
+        //* Prover and verifier compute the same c from the same nonce; another server or nonce gets another c.
+        let zkp = ZKP::modp();
+        let x = SecretScalar::new(BigUint::from(6u32));
+        let ((y1, y2), (r1, r2)) = (zkp.commit(&x), zkp.commit(&zkp.random_secret()));
+        let nonce = verifier_nonce();
+        assert_eq!(nonce.len(), VERIFIER_NONCE_LEN);
+
+        let c = zkp.challenge(&zkp.interactive_transcript("alice", "auth.example", &nonce), &y1, &y2, &r1, &r2);
+        assert_eq!(c, zkp.challenge(&zkp.interactive_transcript("alice", "auth.example", &nonce), &y1, &y2, &r1, &r2));
+        assert_ne!(c, zkp.challenge(&zkp.interactive_transcript("alice", "evil.example", &nonce), &y1, &y2, &r1, &r2));
+        assert_ne!(c, zkp.challenge(&zkp.interactive_transcript("alice", "auth.example", &verifier_nonce()), &y1, &y2, &r1, &r2));
+    }
+}
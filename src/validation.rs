@@ -0,0 +1,223 @@
+// Validation of values received from the other party.
+// Everything that comes off the wire goes through here before it is stored or verified.
+
+// Group elements (y1, y2, r1, r2)
+// - must decode: exactly element_len() bytes, one encoding per element
+// - must not be the identity (y1 = 1 makes any x a valid secret)
+// - mod p: 1 < y < p and y^q = 1 mod p (element of the order-q subgroup)
+// Scalars (c, s)
+// - exactly scalar_len() bytes
+// - 0 <= s < q
+
+use std::fmt;
+use num_bigint::BigUint;
+use crate::group::Group;
+use crate::zkp_cp::ZKP;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    Malformed, //* Bytes are not the encoding of a group element or scalar (wrong length, >= p, ...).
+    Identity, //* Identity element (1 mod p).
+    OutOfRange, //* y = 0 or y >= p.
+    NotInSubgroup, //* y^q != 1 mod p.
+    ScalarOutOfRange, //* s >= q.
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            ValidationError::Malformed => "malformed encoding",
+            ValidationError::Identity => "identity element",
+            ValidationError::OutOfRange => "element out of range",
+            ValidationError::NotInSubgroup => "element not in the order-q subgroup",
+            ValidationError::ScalarOutOfRange => "scalar out of range",
+        };
+        f.write_str(reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+//* Group parameters that can not be used (e.g. received from a server).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError {
+    UnsupportedVersion(u32), //* Protocol version this build does not speak.
+    UnknownGroup(String),
+    Mismatch(&'static str), //* Field differs from the group it claims to be.
+    Missing(&'static str), //* Required field absent (e.g. from a parameter file).
+    Invalid(&'static str), //* Not a valid group (e.g. p is not prime).
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamError::UnsupportedVersion(version) => write!(f, "unsupported protocol version {}", version),
+            ParamError::UnknownGroup(name) => write!(f, "unknown group {}", name),
+            ParamError::Mismatch(field) => write!(f, "{} does not match the group", field),
+            ParamError::Missing(field) => write!(f, "missing {}", field),
+            ParamError::Invalid(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+impl<G: Group> ZKP<G> {
+    pub fn validate_element(&self, e: &G::Element) -> Result<(), ValidationError> {
+        self.group.check_element(e)
+    }
+
+    pub fn validate_scalar(&self, s: &BigUint) -> Result<(), ValidationError> {
+        if s < self.group.order() {
+            Ok(())
+        } else {
+            Err(ValidationError::ScalarOutOfRange)
+        }
+    }
+
+    //* Group well formed; alpha, beta distinct elements of order q, alpha the generator.
+    pub fn check_params(&self) -> Result<(), ParamError> {
+        self.group.check_params()?;
+
+        if self.alpha != self.group.generator() {
+            return Err(ParamError::Mismatch("alpha"));
+        }
+        self.validate_element(&self.alpha).map_err(|_| ParamError::Invalid("alpha is not of order q"))?;
+        self.validate_element(&self.beta).map_err(|_| ParamError::Invalid("beta is not of order q"))?;
+        if self.alpha == self.beta {
+            return Err(ParamError::Invalid("alpha = beta"));
+        }
+        Ok(())
+    }
+
+    //* beta is the element hashed from seed (Group::hash_to_group); nobody knows log_alpha(beta).
+    pub fn check_beta(&self, seed: &[u8]) -> bool {
+        self.validate_element(&self.beta).is_ok() && self.group.hash_to_group(seed).as_ref() == Some(&self.beta)
+    }
+
+    //* decode + validate
+    pub fn parse_element(&self, bytes: &[u8]) -> Result<G::Element, ValidationError> {
+        let e = self.group.decode(bytes).ok_or(ValidationError::Malformed)?;
+        self.validate_element(&e)?;
+        Ok(e)
+    }
+
+    pub fn parse_scalar(&self, bytes: &[u8]) -> Result<BigUint, ValidationError> {
+        let s = self.group.decode_scalar(bytes).ok_or(ValidationError::Malformed)?;
+        self.validate_scalar(&s)?;
+        Ok(s)
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::zkp_cp::ModPGroup;
+
+    //* p = 23, q = 11; subgroup = quadratic residues {1, 2, 3, 4, 6, 8, 9, 12, 13, 16, 18}
+    fn tiny() -> ZKP {
+        ZKP {
+            group: ModPGroup {
+                p: BigUint::from(23u32),
+                q: BigUint::from(11u32),
+                g: BigUint::from(4u32),
+                name: None,
+            },
+            alpha: BigUint::from(4u32),
+            beta: BigUint::from(9u32),
+            tables: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_modp_tiny() {
+        let zkp = tiny();
+        let check = |n: u32| zkp.validate_element(&BigUint::from(n));
+
+        assert_eq!(check(0), Err(ValidationError::OutOfRange));
+        assert_eq!(check(1), Err(ValidationError::Identity));
+        assert_eq!(check(23), Err(ValidationError::OutOfRange));
+        assert_eq!(check(25), Err(ValidationError::OutOfRange)); //* 25 = 2 mod p; still rejected
+        assert_eq!(check(22), Err(ValidationError::NotInSubgroup)); //* -1, order 2
+        assert_eq!(check(5), Err(ValidationError::NotInSubgroup));
+
+        for n in [2u32, 3, 4, 6, 8, 9, 12, 13, 16, 18] {
+            assert_eq!(check(n), Ok(()));
+        }
+
+        assert_eq!(zkp.validate_scalar(&BigUint::from(10u32)), Ok(()));
+        assert_eq!(zkp.validate_scalar(&BigUint::from(11u32)), Err(ValidationError::ScalarOutOfRange));
+    }
+
+    #[test]
+    fn test_parse_modp() {
+        let zkp = ZKP::modp();
+        let (y1, y2) = zkp.commit(&zkp.random_secret());
+        let bytes = zkp.group.encode(&y1);
+
+        assert_eq!(zkp.parse_element(&bytes), Ok(y1));
+        assert_eq!(zkp.parse_element(&zkp.group.encode(&y2)), Ok(y2));
+        let encode = |n: u32| zkp.group.encode(&BigUint::from(n));
+        assert_eq!(zkp.parse_element(&encode(1)), Err(ValidationError::Identity));
+        assert_eq!(zkp.parse_element(&encode(0)), Err(ValidationError::OutOfRange));
+
+        //* p - 1 has order 2
+        let minus_one = &zkp.group.p - 1u32;
+        assert_eq!(zkp.parse_element(&zkp.group.encode(&minus_one)), Err(ValidationError::NotInSubgroup));
+
+        //* Non-canonical: short, long (leading zero), p itself
+        assert_eq!(zkp.parse_element(&[1u8]), Err(ValidationError::Malformed));
+        assert_eq!(zkp.parse_element(&[]), Err(ValidationError::Malformed));
+        assert_eq!(zkp.parse_element(&[&[0u8][..], &bytes].concat()), Err(ValidationError::Malformed));
+        assert_eq!(zkp.parse_element(&zkp.group.p.to_bytes_be()), Err(ValidationError::Malformed));
+
+        let q = zkp.group.q.clone();
+        assert_eq!(zkp.parse_scalar(&zkp.group.encode_scalar(&(&q - 1u32))), Ok(&q - 1u32));
+        assert_eq!(zkp.parse_scalar(&zkp.group.encode_scalar(&q)), Err(ValidationError::ScalarOutOfRange));
+        assert_eq!(zkp.parse_scalar(&[1u8]), Err(ValidationError::Malformed));
+        assert_eq!(zkp.parse_scalar(&[&[0u8][..], &zkp.group.encode_scalar(&q)].concat()), Err(ValidationError::Malformed));
+    }
+
+    #[test]
+    fn test_parse_ristretto255() {
+        let zkp = ZKP::ristretto255();
+        let (y1, _) = zkp.commit(&zkp.random_secret());
+
+        assert_eq!(zkp.parse_element(&zkp.group.encode(&y1)), Ok(y1));
+        assert_eq!(zkp.parse_element(&[0u8; 32]), Err(ValidationError::Identity));
+        assert_eq!(zkp.parse_element(&[0xffu8; 32]), Err(ValidationError::Malformed));
+        assert_eq!(zkp.parse_element(&[1u8; 3]), Err(ValidationError::Malformed));
+
+        let q = zkp.group.order().clone();
+        assert_eq!(zkp.parse_scalar(&zkp.group.encode_scalar(&q)), Err(ValidationError::ScalarOutOfRange));
+    }
+
+    #[test]
+    fn test_check_params() {
+        assert_eq!(ZKP::modp().check_params(), Ok(()));
+        assert_eq!(ZKP::ristretto255().check_params(), Ok(()));
+        assert_eq!(tiny().check_params(), Ok(()));
+
+        assert_eq!(ZKP { beta: BigUint::from(4u32), ..tiny() }.check_params(), Err(ParamError::Invalid("alpha = beta")));
+        assert_eq!(ZKP { beta: BigUint::from(1u32), ..tiny() }.check_params(), Err(ParamError::Invalid("beta is not of order q")));
+        assert_eq!(ZKP { beta: BigUint::from(5u32), ..tiny() }.check_params(), Err(ParamError::Invalid("beta is not of order q")));
+        assert_eq!(ZKP { alpha: BigUint::from(9u32), ..tiny() }.check_params(), Err(ParamError::Mismatch("alpha")));
+    }
+
+    #[test]
+    fn test_check_beta() {
+        assert!(ZKP::modp().check_beta(&crate::params::beta_seed("rfc5114-1024-160")));
+        assert!(ZKP::ristretto255().check_beta(b"zkp_chaum_pedersen/ristretto255/beta"));
+        assert!(!ZKP::modp().check_beta(b"some other seed"));
+
+        //* beta = alpha^x for a known x; valid element, but not the derived one
+        let zkp = ZKP::modp();
+        let trapdoor = ZKP { beta: zkp.pow(&zkp.alpha, &BigUint::from(0x5d8de022157b32099dc1u128)), ..ZKP::modp() };
+        assert_eq!(trapdoor.check_params(), Ok(()));
+        assert!(!trapdoor.check_beta(&crate::params::beta_seed("rfc5114-1024-160")));
+    }
+}
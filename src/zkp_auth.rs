@@ -1,3 +1,58 @@
+/// * Public parameters of the server; the client checks them before deriving anything.
+/// * group: Group::name(); the registry name of a named group (e.g. "rfc5114-2048-256"), "modp-<bits>" for other prime field groups, or "ristretto255"
+/// * p: field modulus (2^255 - 19 for ristretto255), q: group order; big-endian
+/// * alpha, beta: generators in the group's element encoding
+/// * beta_seed: beta = Group::hash_to_group(beta_seed); the client re-derives beta from it (ZKP::check_beta)
+/// * Every element (y1, y2, r1, r2, alpha, beta) is Group::encode(): fixed length, the byte length of p (32 for ristretto255).
+/// * Every scalar (c, s) is padded to the byte length of q. Any other length, or a value >= p or q, is rejected.
+///
+/// * empty body
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParametersRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ParametersResponse {
+    #[prost(string, tag = "1")]
+    pub group: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub protocol_version: u32,
+    #[prost(bytes = "vec", tag = "3")]
+    pub p: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub q: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "5")]
+    pub alpha: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "6")]
+    pub beta: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub beta_seed: ::prost::alloc::vec::Vec<u8>,
+}
+/// * Password -> x; x = Argon2id(password, salt) mod q
+/// * version selects the derivation; the costs can be raised without breaking existing users.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KdfParameters {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint32, tag = "2")]
+    pub memory_kib: u32,
+    #[prost(uint32, tag = "3")]
+    pub iterations: u32,
+    #[prost(uint32, tag = "4")]
+    pub parallelism: u32,
+}
+/// * One-shot proof that log_alpha(y1) = log_beta(y2) (Fiat-Shamir); c is recomputed by the verifier.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct NonInteractiveProof {
+    #[prost(bytes = "vec", tag = "1")]
+    pub r1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub r2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub s: ::prost::alloc::vec::Vec<u8>,
+}
 /// * Register; prover will register to the server with y1,y2.
 /// * y1 = alpha^x mod p
 /// * y2 = beta^x mod p
@@ -12,15 +67,78 @@ pub struct RegisterRequest {
     pub y1: ::prost::alloc::vec::Vec<u8>,
     #[prost(bytes = "vec", tag = "3")]
     pub y2: ::prost::alloc::vec::Vec<u8>,
+    /// * Random, per user; the server stores it and serves it at login time.
+    #[prost(bytes = "vec", tag = "4")]
+    pub salt: ::prost::alloc::vec::Vec<u8>,
+    /// * Must meet the server's floor (GetKdfParameters).
+    #[prost(message, optional, tag = "5")]
+    pub kdf: ::core::option::Option<KdfParameters>,
+    /// * Proof of possession of x; transcript = register, group, username, server identity. Optional unless the server requires it.
+    #[prost(message, optional, tag = "6")]
+    pub proof: ::core::option::Option<NonInteractiveProof>,
 }
 /// * empty body
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RegisterResponse {}
+/// * Rotate: replace y1, y2 (and salt, KDF costs) of an existing user.
+/// * r1, r2, s: one-shot proof for the current y1, y2, like AuthenticateWithProof.
+/// * transcript = rotate, group, username, server identity, timestamp, new y1, y2, salt, kdf, new_proof; the proof only installs these values.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RotateCredentialsRequest {
+    #[prost(string, tag = "1")]
+    pub username: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub r1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub r2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub s: ::prost::alloc::vec::Vec<u8>,
+    /// * Unix seconds; must be close to the server clock.
+    #[prost(uint64, tag = "5")]
+    pub timestamp: u64,
+    /// * New credentials; same rules as RegisterRequest.
+    #[prost(bytes = "vec", tag = "6")]
+    pub y1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "7")]
+    pub y2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "8")]
+    pub salt: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag = "9")]
+    pub kdf: ::core::option::Option<KdfParameters>,
+    /// * Proof of possession of the new x; transcript = rotate-key, group, username, server identity, timestamp. Optional unless the server requires it (like RegisterRequest.proof).
+    #[prost(message, optional, tag = "10")]
+    pub new_proof: ::core::option::Option<NonInteractiveProof>,
+}
+/// * empty body
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RotateCredentialsResponse {}
+/// * Salt and KDF costs of a user; asked before deriving x at login time.
+/// * Unknown users get a stable fake salt; their logins and rotations fail like a wrong password (PermissionDenied).
+/// * Only Register tells a taken username apart (AlreadyExists).
+/// * The returned costs of an unknown user are the server's current floor (use them to register).
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KdfParametersRequest {
+    #[prost(string, tag = "1")]
+    pub username: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct KdfParametersResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub salt: ::prost::alloc::vec::Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub kdf: ::core::option::Option<KdfParameters>,
+}
 /// * Challenge Request: Prover will ask for a challenge by sending r1, r2
 /// * y1 = alpha^k mod p
 /// * y2 = beta^k mod p
 /// * Verifier will send the challenge 'c' back.
+/// * c = transcript(interactive, group, username, server identity, nonce, y1, y2, r1, r2); see transcript.rs
+/// * The prover recomputes c from nonce and refuses to answer if it differs (challenge meant for another server or user).
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct AuthenticationChallengeRequest {
@@ -39,6 +157,9 @@ pub struct AuthenticationChallengeResponse {
     pub auth_id: ::prost::alloc::string::String,
     #[prost(bytes = "vec", tag = "2")]
     pub c: ::prost::alloc::vec::Vec<u8>,
+    /// * Verifier randomness absorbed into the transcript before c.
+    #[prost(bytes = "vec", tag = "3")]
+    pub nonce: ::prost::alloc::vec::Vec<u8>,
 }
 /// * Solution & Verify: Prover will send the solution s.
 /// * s = k - c*x mod q
@@ -56,7 +177,54 @@ pub struct AuthenticationAnswerRequest {
 pub struct AuthenticationAnswerResponse {
     #[prost(string, tag = "1")]
     pub session_id: ::prost::alloc::string::String,
+    /// * Unix seconds; RefreshSession before then.
+    #[prost(uint64, tag = "2")]
+    pub expires_at: u64,
+}
+/// * One-shot login (Fiat-Shamir): prover squeezes c from the transcript itself.
+/// * transcript = login, group, username, server identity, timestamp, y1, y2, r1, r2; binds the proof to this user, this server and this moment.
+/// * Verifier recomputes c and answers with a 'session ID' like VerifyAuthentication.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AuthenticationProofRequest {
+    #[prost(string, tag = "1")]
+    pub username: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub r1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub r2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub s: ::prost::alloc::vec::Vec<u8>,
+    /// * Unix seconds; must be close to the server clock.
+    #[prost(uint64, tag = "5")]
+    pub timestamp: u64,
+}
+/// * Sessions: other services check a 'session ID' with the server.
+/// * ValidateSession: who owns it and until when.
+/// * RefreshSession: extend it by another lifetime.
+/// * Logout: revoke it.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionRequest {
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SessionResponse {
+    #[prost(string, tag = "1")]
+    pub username: ::prost::alloc::string::String,
+    /// * Unix seconds
+    #[prost(uint64, tag = "2")]
+    pub created_at: u64,
+    /// * Unix seconds
+    #[prost(uint64, tag = "3")]
+    pub expires_at: u64,
 }
+/// * empty body
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogoutResponse {}
 /// Generated client implementations.
 pub mod auth_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -144,6 +312,56 @@ pub mod auth_service_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
+        pub async fn get_parameters(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ParametersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ParametersResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/GetParameters",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("zkp_auth.AuthService", "GetParameters"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_kdf_parameters(
+            &mut self,
+            request: impl tonic::IntoRequest<super::KdfParametersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::KdfParametersResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/GetKdfParameters",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("zkp_auth.AuthService", "GetKdfParameters"));
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn register(
             &mut self,
             request: impl tonic::IntoRequest<super::RegisterRequest>,
@@ -169,6 +387,31 @@ pub mod auth_service_client {
                 .insert(GrpcMethod::new("zkp_auth.AuthService", "Register"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn rotate_credentials(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RotateCredentialsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RotateCredentialsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/RotateCredentials",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("zkp_auth.AuthService", "RotateCredentials"));
+            self.inner.unary(req, path, codec).await
+        }
         pub async fn create_authentication_challenge(
             &mut self,
             request: impl tonic::IntoRequest<super::AuthenticationChallengeRequest>,
@@ -224,6 +467,105 @@ pub mod auth_service_client {
                 .insert(GrpcMethod::new("zkp_auth.AuthService", "VerifyAuthentication"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn authenticate_with_proof(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AuthenticationProofRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AuthenticationAnswerResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/AuthenticateWithProof",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("zkp_auth.AuthService", "AuthenticateWithProof"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn validate_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SessionResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/ValidateSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("zkp_auth.AuthService", "ValidateSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn refresh_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SessionResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/RefreshSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("zkp_auth.AuthService", "RefreshSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn logout(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::LogoutResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/zkp_auth.AuthService/Logout",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("zkp_auth.AuthService", "Logout"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -233,6 +575,20 @@ pub mod auth_service_server {
     /// Generated trait containing gRPC methods that should be implemented for use with AuthServiceServer.
     #[async_trait]
     pub trait AuthService: Send + Sync + 'static {
+        async fn get_parameters(
+            &self,
+            request: tonic::Request<super::ParametersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ParametersResponse>,
+            tonic::Status,
+        >;
+        async fn get_kdf_parameters(
+            &self,
+            request: tonic::Request<super::KdfParametersRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::KdfParametersResponse>,
+            tonic::Status,
+        >;
         async fn register(
             &self,
             request: tonic::Request<super::RegisterRequest>,
@@ -240,6 +596,13 @@ pub mod auth_service_server {
             tonic::Response<super::RegisterResponse>,
             tonic::Status,
         >;
+        async fn rotate_credentials(
+            &self,
+            request: tonic::Request<super::RotateCredentialsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RotateCredentialsResponse>,
+            tonic::Status,
+        >;
         async fn create_authentication_challenge(
             &self,
             request: tonic::Request<super::AuthenticationChallengeRequest>,
@@ -254,6 +617,25 @@ pub mod auth_service_server {
             tonic::Response<super::AuthenticationAnswerResponse>,
             tonic::Status,
         >;
+        async fn authenticate_with_proof(
+            &self,
+            request: tonic::Request<super::AuthenticationProofRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AuthenticationAnswerResponse>,
+            tonic::Status,
+        >;
+        async fn validate_session(
+            &self,
+            request: tonic::Request<super::SessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::SessionResponse>, tonic::Status>;
+        async fn refresh_session(
+            &self,
+            request: tonic::Request<super::SessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::SessionResponse>, tonic::Status>;
+        async fn logout(
+            &self,
+            request: tonic::Request<super::SessionRequest>,
+        ) -> std::result::Result<tonic::Response<super::LogoutResponse>, tonic::Status>;
     }
     /// * Definition of Server service
     /// * Tonic will compile this also for a client side.
@@ -336,6 +718,99 @@ pub mod auth_service_server {
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             let inner = self.inner.clone();
             match req.uri().path() {
+                "/zkp_auth.AuthService/GetParameters" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetParametersSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::ParametersRequest>
+                    for GetParametersSvc<T> {
+                        type Response = super::ParametersResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ParametersRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::get_parameters(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetParametersSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/zkp_auth.AuthService/GetKdfParameters" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetKdfParametersSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::KdfParametersRequest>
+                    for GetKdfParametersSvc<T> {
+                        type Response = super::KdfParametersResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::KdfParametersRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::get_kdf_parameters(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetKdfParametersSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/zkp_auth.AuthService/Register" => {
                     #[allow(non_camel_case_types)]
                     struct RegisterSvc<T: AuthService>(pub Arc<T>);
@@ -382,6 +857,53 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/zkp_auth.AuthService/RotateCredentials" => {
+                    #[allow(non_camel_case_types)]
+                    struct RotateCredentialsSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::RotateCredentialsRequest>
+                    for RotateCredentialsSvc<T> {
+                        type Response = super::RotateCredentialsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RotateCredentialsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::rotate_credentials(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RotateCredentialsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/zkp_auth.AuthService/CreateAuthenticationChallenge" => {
                     #[allow(non_camel_case_types)]
                     struct CreateAuthenticationChallengeSvc<T: AuthService>(pub Arc<T>);
@@ -481,6 +1003,191 @@ pub mod auth_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/zkp_auth.AuthService/AuthenticateWithProof" => {
+                    #[allow(non_camel_case_types)]
+                    struct AuthenticateWithProofSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::AuthenticationProofRequest>
+                    for AuthenticateWithProofSvc<T> {
+                        type Response = super::AuthenticationAnswerResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AuthenticationProofRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::authenticate_with_proof(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AuthenticateWithProofSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/zkp_auth.AuthService/ValidateSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct ValidateSessionSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::SessionRequest>
+                    for ValidateSessionSvc<T> {
+                        type Response = super::SessionResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::validate_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ValidateSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/zkp_auth.AuthService/RefreshSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct RefreshSessionSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::SessionRequest>
+                    for RefreshSessionSvc<T> {
+                        type Response = super::SessionResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::refresh_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RefreshSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/zkp_auth.AuthService/Logout" => {
+                    #[allow(non_camel_case_types)]
+                    struct LogoutSvc<T: AuthService>(pub Arc<T>);
+                    impl<
+                        T: AuthService,
+                    > tonic::server::UnaryService<super::SessionRequest>
+                    for LogoutSvc<T> {
+                        type Response = super::LogoutResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as AuthService>::logout(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = LogoutSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
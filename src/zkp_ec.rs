@@ -0,0 +1,222 @@
+// Elliptic-curve backend for the Chaum-Pedersen Protocol.
+// Group: Ristretto255 (prime order subgroup of Curve25519)
+
+// Same protocol as zkp_cp, but written additively:
+// - pow(a, x) = x * a
+// - mul(a, b) = a + b
+
+// Points are 32 bytes on the wire; scalars are reduced mod q (253 bits).
+
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoBasepointTable, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use hex_literal::hex;
+use num_bigint::BigUint;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+use crate::ct;
+use crate::group::Group;
+use crate::secret::SecretScalar;
+use crate::validation::{ParamError, ValidationError};
+use crate::zkp_cp::ZKP;
+
+//* Seed for the second generator; nobody knows log_alpha(beta).
+const BETA_SEED: &[u8] = b"zkp_chaum_pedersen/ristretto255/beta";
+
+//* p = 2^255 - 19
+const FIELD_MODULUS: [u8; 32] = hex!("7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed");
+
+//* q = 2^252 + 27742317777372353535851937790883648493
+const ORDER: [u8; 32] = hex!("1000000000000000000000000000000014def9dea2f79cd65812631a5cf5d3ed");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ristretto255 {
+    q: BigUint,
+}
+
+impl Default for Ristretto255 {
+    fn default() -> Self {
+        Ristretto255 {
+            q: BigUint::from_bytes_be(&ORDER),
+        }
+    }
+}
+
+impl Ristretto255 {
+    //* BigUint -> Scalar (little-endian, reduced mod q)
+    //* Up to 256 bits (every scalar of the protocol) the reduction is done by Scalar, in constant time.
+    pub fn to_scalar(&self, n: &BigUint) -> Scalar {
+        let reduced = if n.bits() > 256 { (n % &self.q).to_bytes_le() } else { n.to_bytes_le() };
+        let mut bytes = [0u8; 32];
+        bytes[..reduced.len()].copy_from_slice(&reduced);
+        Scalar::from_bytes_mod_order(bytes)
+    }
+
+    //* Secret -> Scalar without a BigUint copy; the bytes and the Scalar are wiped.
+    fn secret_scalar(&self, n: &SecretScalar) -> Zeroizing<Scalar> {
+        let reduced = (n.bits() > 256).then(|| ct::reduce(n.le_bytes(), &self.q));
+        let le = reduced.as_ref().unwrap_or(n).le_bytes();
+        let mut bytes = Zeroizing::new([0u8; 32]);
+        bytes[..le.len()].copy_from_slice(le);
+        Zeroizing::new(Scalar::from_bytes_mod_order(*bytes))
+    }
+}
+
+impl Group for Ristretto255 {
+    type Element = RistrettoPoint;
+    type Table = RistrettoBasepointTable;
+
+    fn name(&self) -> String {
+        "ristretto255".to_string()
+    }
+
+    fn order(&self) -> &BigUint {
+        &self.q
+    }
+
+    fn generator(&self) -> RistrettoPoint {
+        RISTRETTO_BASEPOINT_POINT
+    }
+
+    fn modulus(&self) -> BigUint {
+        BigUint::from_bytes_be(&FIELD_MODULUS)
+    }
+
+    //* Fixed curve: exactly the encoded parameters of the default group.
+    fn decode_params(fields: &[Vec<u8>]) -> Option<Self> {
+        let group = Ristretto255::default();
+        (group.encode_params() == fields).then_some(group)
+    }
+
+    fn pow(&self, n: &RistrettoPoint, exp: &BigUint) -> RistrettoPoint {
+        n * self.to_scalar(exp)
+    }
+
+    //* Scalar multiplication in curve25519-dalek is constant time already.
+    fn pow_secret(&self, n: &RistrettoPoint, exp: &SecretScalar) -> RistrettoPoint {
+        let scalar = self.secret_scalar(exp);
+        n * *scalar
+    }
+
+    fn mul(&self, a: &RistrettoPoint, b: &RistrettoPoint) -> RistrettoPoint {
+        a + b
+    }
+
+    //* Radix-16 tables of curve25519-dalek; constant time, so both flavours are the same.
+    fn precompute(&self, base: &RistrettoPoint) -> RistrettoBasepointTable {
+        RistrettoBasepointTable::create(base)
+    }
+
+    fn pow_table(&self, table: &RistrettoBasepointTable, exp: &BigUint) -> RistrettoPoint {
+        table * &self.to_scalar(exp)
+    }
+
+    fn pow_table_secret(&self, table: &RistrettoBasepointTable, exp: &SecretScalar) -> RistrettoPoint {
+        table * &*self.secret_scalar(exp)
+    }
+
+    //* Straus / Pippenger in curve25519-dalek
+    fn multi_pow(&self, terms: &[(&RistrettoPoint, &BigUint)]) -> RistrettoPoint {
+        RistrettoPoint::vartime_multiscalar_mul(terms.iter().map(|(_, exp)| self.to_scalar(exp)), terms.iter().map(|(n, _)| *n))
+    }
+
+    fn element_len(&self) -> usize {
+        32
+    }
+
+    //* Encoding; 32 bytes compressed point.
+    fn encode(&self, e: &RistrettoPoint) -> Vec<u8> {
+        e.compress().to_bytes().to_vec()
+    }
+
+    //* decompress() rejects non-canonical encodings.
+    fn decode(&self, bytes: &[u8]) -> Option<RistrettoPoint> {
+        CompressedRistretto::from_slice(bytes).ok()?.decompress()
+    }
+
+    //* Every decoded point is in the prime-order group; only the identity is left to reject.
+    fn check_element(&self, e: &RistrettoPoint) -> Result<(), ValidationError> {
+        if *e == RistrettoPoint::identity() {
+            Err(ValidationError::Identity)
+        } else {
+            Ok(())
+        }
+    }
+
+    //* Fixed curve; only q can be off (a hand-built Ristretto255).
+    fn check_params(&self) -> Result<(), ParamError> {
+        if *self == Ristretto255::default() {
+            Ok(())
+        } else {
+            Err(ParamError::Invalid("q is not the order of ristretto255"))
+        }
+    }
+
+    //* Elligator map of SHA-512(seed)
+    fn hash_to_group(&self, seed: &[u8]) -> Option<RistrettoPoint> {
+        Some(RistrettoPoint::hash_from_bytes::<Sha512>(seed))
+    }
+
+    fn beta_seed(&self) -> Option<Vec<u8>> {
+        Some(BETA_SEED.to_vec())
+    }
+}
+
+impl ZKP<Ristretto255> {
+    pub fn ristretto255() -> Self {
+        let group = Ristretto255::default();
+        //* Public
+        let alpha: RistrettoPoint = group.generator();
+        //* beta is hashed into the group instead of alpha^x; x is unknown to everyone.
+        let beta: RistrettoPoint = RistrettoPoint::hash_from_bytes::<Sha512>(BETA_SEED); //* = hash_to_group(BETA_SEED)
+
+        ZKP { group, alpha, beta, tables: None }
+    }
+}
+
+
+//* TEST
+//==========================
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encoding() {
+        let zkp = ZKP::ristretto255();
+        let x: BigUint = zkp.random_scalar();
+        let y1: RistrettoPoint = zkp.pow(&zkp.alpha, &x);
+
+        //* 32 bytes each
+        assert_eq!(zkp.group.encode(&y1).len(), 32);
+        assert_eq!(zkp.group.encode_scalar(&x).len(), 32);
+
+        //* Round trip
+        assert_eq!(zkp.group.decode(&zkp.group.encode(&y1)), Some(y1));
+        assert_eq!(zkp.group.decode_scalar(&zkp.group.encode_scalar(&x)), Some(x));
+
+        //* Malformed input
+        assert_eq!(zkp.group.decode(&[0xffu8; 32]), None);
+        assert_eq!(zkp.group.decode(&[1u8; 31]), None);
+        assert_eq!(zkp.group.decode_scalar(&[1u8; 31]), None);
+    }
+
+    #[test]
+    fn test_order() {
+        let group = Ristretto255::default();
+
+        //* q * G = identity; (q - 1) * G = -G
+        let q = group.order().clone();
+        assert_eq!(group.pow(&group.generator(), &q), RistrettoPoint::default());
+        assert_eq!(group.pow(&group.generator(), &(q - 1u32)), -group.generator());
+    }
+
+    #[test]
+    fn test_beta_seed() {
+        let zkp = ZKP::ristretto255();
+        assert!(zkp.check_beta(&zkp.group.beta_seed().unwrap()));
+        assert!(!zkp.check_beta(b"zkp_chaum_pedersen/ristretto255/alpha"));
+    }
+}